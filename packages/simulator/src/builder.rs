@@ -0,0 +1,310 @@
+use std::fmt;
+
+use crate::particle::Particle;
+use crate::particle_generator::{self, GenerationOptions};
+use crate::simulation::{Simulation, SimulationParams};
+use crate::spatial_distribution::SpatialDistributionError;
+use crate::vector2::Vector2;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BuildError {
+    /// Unset, or a side that is not positive and finite
+    InvalidWorldSize,
+    InvalidTimeStep,
+    InvalidGravity,
+    InvalidEpsilon,
+    InvalidTheta,
+    /// Generated particles couldn't be placed inside the world
+    Distribution(SpatialDistributionError),
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BuildError::InvalidWorldSize => {
+                write!(f, "World size must be positive and finite")
+            }
+            BuildError::InvalidTimeStep => write!(f, "Time step must be positive and finite"),
+            BuildError::InvalidGravity => write!(f, "Gravity must be finite"),
+            BuildError::InvalidEpsilon => {
+                write!(f, "Softening must be non-negative and finite")
+            }
+            BuildError::InvalidTheta => write!(f, "Theta must be non-negative and finite"),
+            BuildError::Distribution(error) => error.fmt(f),
+        }
+    }
+}
+
+/// Particles in the order they were added; generated ones are only drawn in
+/// `build`, once the world size is known.
+#[derive(Debug, Clone)]
+enum ParticleSource {
+    Given(Vec<Particle>),
+    Generated {
+        number: usize,
+        mass: f32,
+        mass_deviation: f32,
+        diameter: f32,
+        options: GenerationOptions,
+    },
+}
+
+/// Assembles a simulation from Rust: particles, the physics `run` steps
+/// with, and optionally the opening angle and a seed for sampled
+/// diagnostics. The world size and time step are required; gravity and
+/// softening left unset stay zero, as in a fresh `Simulation`.
+#[derive(Debug, Clone, Default)]
+pub struct SimulationBuilder {
+    sources: Vec<ParticleSource>,
+    params: SimulationParams,
+    theta: Option<f32>,
+    seed: Option<u64>,
+}
+
+impl SimulationBuilder {
+    pub fn new() -> SimulationBuilder {
+        SimulationBuilder::default()
+    }
+
+    /// Adds particles after any already added.
+    pub fn with_particles(
+        mut self,
+        particles: impl IntoIterator<Item = Particle>,
+    ) -> SimulationBuilder {
+        self.sources
+            .push(ParticleSource::Given(particles.into_iter().collect()));
+        self
+    }
+
+    /// Adds `number` particles generated as `generate_particles` does, inside
+    /// the world size, whether it is set before or after.
+    pub fn with_generated_particles(
+        mut self,
+        number: usize,
+        mass: f32,
+        mass_deviation: f32,
+        diameter: f32,
+        options: &GenerationOptions,
+    ) -> SimulationBuilder {
+        self.sources.push(ParticleSource::Generated {
+            number,
+            mass,
+            mass_deviation,
+            diameter,
+            options: *options,
+        });
+        self
+    }
+
+    pub fn with_world_size(mut self, width: f32, height: f32) -> SimulationBuilder {
+        self.params.world_size = Vector2::new(width, height);
+        self
+    }
+
+    pub fn with_gravity(mut self, gravity: f32) -> SimulationBuilder {
+        self.params.gravity = gravity;
+        self
+    }
+
+    pub fn with_epsilon(mut self, epsilon: f32) -> SimulationBuilder {
+        self.params.epsilon = epsilon;
+        self
+    }
+
+    pub fn with_time_step(mut self, time_step: f32) -> SimulationBuilder {
+        self.params.time_step = time_step;
+        self
+    }
+
+    pub fn with_theta(mut self, theta: f32) -> SimulationBuilder {
+        self.theta = Some(theta);
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> SimulationBuilder {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn build(self) -> Result<Simulation, BuildError> {
+        let world_size = self.params.world_size;
+        if ![world_size.x, world_size.y]
+            .iter()
+            .all(|&side| side > 0.0 && side.is_finite())
+        {
+            return Err(BuildError::InvalidWorldSize);
+        }
+        if !(self.params.time_step > 0.0 && self.params.time_step.is_finite()) {
+            return Err(BuildError::InvalidTimeStep);
+        }
+        if !self.params.gravity.is_finite() {
+            return Err(BuildError::InvalidGravity);
+        }
+        if !(self.params.epsilon >= 0.0 && self.params.epsilon.is_finite()) {
+            return Err(BuildError::InvalidEpsilon);
+        }
+        if matches!(self.theta, Some(theta) if !(theta >= 0.0 && theta.is_finite())) {
+            return Err(BuildError::InvalidTheta);
+        }
+
+        let mut particles = Vec::new();
+        for source in self.sources {
+            match source {
+                ParticleSource::Given(given) => particles.extend(given),
+                ParticleSource::Generated {
+                    number,
+                    mass,
+                    mass_deviation,
+                    diameter,
+                    options,
+                } => particles.extend(
+                    particle_generator::generate(
+                        number,
+                        world_size,
+                        mass,
+                        mass_deviation,
+                        diameter,
+                        &options,
+                    )
+                    .map_err(BuildError::Distribution)?,
+                ),
+            }
+        }
+
+        let mut sim = Simulation::new(particles);
+        sim.params = self.params;
+        if let Some(theta) = self.theta {
+            sim.theta = theta;
+        }
+        if let Some(seed) = self.seed {
+            sim.seed(seed);
+        }
+        Ok(sim)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn particle(x: f32, y: f32) -> Particle {
+        Particle::new(
+            1.0,
+            1.0,
+            Vector2::new(x, y),
+            Vector2::new(0.0, 0.0),
+            [255.0, 255.0, 255.0],
+        )
+    }
+
+    #[test]
+    fn test_build_and_run() {
+        let mut sim = SimulationBuilder::new()
+            .with_world_size(100.0, 100.0)
+            .with_gravity(1.0)
+            .with_epsilon(0.5)
+            .with_time_step(0.01)
+            .with_theta(0.3)
+            .with_particles([particle(40.0, 50.0), particle(60.0, 50.0)])
+            .build()
+            .unwrap();
+
+        sim.run(10);
+
+        assert_eq!(sim.step_count, 10);
+        assert_eq!(sim.theta, 0.3);
+        assert_eq!(sim.params.time_step, 0.01);
+        // Attraction pulls the pair together symmetrically
+        assert!(sim.positions_x[0] > 40.0);
+        assert!((sim.positions_x[0] + sim.positions_x[1] - 100.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_generated_particles_fill_the_world() {
+        // Generated before the world size is set, which only `build` needs
+        let sim = SimulationBuilder::new()
+            .with_particles([particle(1.0, 1.0)])
+            .with_generated_particles(100, 1.0, 0.0, 1.0, &GenerationOptions::new())
+            .with_world_size(50.0, 20.0)
+            .with_time_step(0.01)
+            .build()
+            .unwrap();
+
+        assert_eq!(sim.count, 101);
+        assert_eq!(sim.positions_x[0], 1.0);
+        assert!(sim.positions_x[1..].iter().any(|&x| x > 1.0));
+        assert!(sim.positions_x.iter().all(|&x| (0.0..=50.0).contains(&x)));
+        assert!(sim.positions_y.iter().all(|&y| (0.0..=20.0).contains(&y)));
+    }
+
+    #[test]
+    fn test_seed_makes_sampling_reproducible() {
+        let particles: Vec<Particle> = (0..50)
+            .map(|i| particle(i as f32, (i * 7 % 50) as f32))
+            .collect();
+        let build = || {
+            SimulationBuilder::new()
+                .with_world_size(50.0, 50.0)
+                .with_time_step(0.01)
+                .with_particles(particles.clone())
+                .with_seed(3)
+                .build()
+                .unwrap()
+        };
+
+        let a = build().force_accuracy(5, 1.0, 0.5, 0.8);
+        let b = build().force_accuracy(5, 1.0, 0.5, 0.8);
+
+        assert_eq!(a.mean_error, b.mean_error);
+    }
+
+    #[test]
+    fn test_missing_world_size_or_time_step_is_rejected() {
+        let builder = SimulationBuilder::new().with_particles([particle(1.0, 1.0)]);
+
+        assert_eq!(
+            builder.clone().with_time_step(0.01).build().unwrap_err(),
+            BuildError::InvalidWorldSize
+        );
+        assert_eq!(
+            builder
+                .clone()
+                .with_world_size(10.0, f32::INFINITY)
+                .with_time_step(0.01)
+                .build()
+                .unwrap_err(),
+            BuildError::InvalidWorldSize
+        );
+        assert_eq!(
+            builder.with_world_size(10.0, 10.0).build().unwrap_err(),
+            BuildError::InvalidTimeStep
+        );
+    }
+
+    #[test]
+    fn test_invalid_physics_is_rejected() {
+        let builder = SimulationBuilder::new()
+            .with_world_size(10.0, 10.0)
+            .with_time_step(0.01);
+
+        for gravity in [f32::NAN, f32::INFINITY] {
+            assert_eq!(
+                builder.clone().with_gravity(gravity).build().unwrap_err(),
+                BuildError::InvalidGravity
+            );
+        }
+        for epsilon in [f32::NAN, -0.1] {
+            assert_eq!(
+                builder.clone().with_epsilon(epsilon).build().unwrap_err(),
+                BuildError::InvalidEpsilon
+            );
+        }
+        for theta in [f32::NAN, -0.5] {
+            assert_eq!(
+                builder.clone().with_theta(theta).build().unwrap_err(),
+                BuildError::InvalidTheta
+            );
+        }
+        assert!(builder.with_gravity(-1.0).build().is_ok());
+    }
+}
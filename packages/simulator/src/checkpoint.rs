@@ -0,0 +1,228 @@
+use std::fmt;
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+use crate::drift_tracker::DriftTracker;
+use crate::simulation::Simulation;
+use crate::snapshot::SnapshotError;
+use crate::utils::binary_utils::{ByteReader, ByteWriter};
+use crate::utils::checksum::crc32;
+
+const MAGIC: &[u8; 8] = b"NBODYCKP";
+
+/// Bumped whenever the layout below changes.
+pub const CHECKPOINT_VERSION: u32 = 2;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CheckpointError {
+    BadMagic,
+    UnsupportedVersion(u32),
+    ChecksumMismatch,
+    Truncated,
+    TrailingBytes,
+    /// The embedded snapshot is invalid
+    Snapshot(SnapshotError),
+}
+
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CheckpointError::BadMagic => write!(f, "Not a simulation checkpoint"),
+            CheckpointError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported checkpoint version {}", version)
+            }
+            CheckpointError::ChecksumMismatch => write!(f, "Checkpoint checksum mismatch"),
+            CheckpointError::Truncated => write!(f, "Checkpoint is truncated"),
+            CheckpointError::TrailingBytes => write!(f, "Unexpected data after checkpoint"),
+            CheckpointError::Snapshot(e) => write!(f, "Invalid checkpoint snapshot: {}", e),
+        }
+    }
+}
+
+/// Everything a run needs to carry on as if it never stopped, all values
+/// little-endian:
+///
+/// ```text
+/// magic "NBODYCKP", version u32, snapshot length u64, snapshot (see
+/// `Simulation::to_bytes`), RNG seed [u8; 32], RNG stream u64, RNG word
+/// position u128, drift flag u8, drift tracker state if the flag is set,
+/// CRC-32 u32 of everything before it
+/// ```
+///
+/// The integrator keeps no state beyond positions and velocities, which the
+/// snapshot already holds bit for bit. Resume with `sim.params` to step
+/// exactly as the interrupted run would have.
+impl Simulation {
+    pub fn to_checkpoint(&self) -> Vec<u8> {
+        let mut writer = ByteWriter::new();
+
+        writer.write_bytes(MAGIC);
+        writer.write_u32(CHECKPOINT_VERSION);
+        let snapshot = self.to_bytes();
+        writer.write_u64(snapshot.len() as u64);
+        writer.write_bytes(&snapshot);
+
+        writer.write_bytes(&self.rng.get_seed());
+        writer.write_u64(self.rng.get_stream());
+        let word_pos = self.rng.get_word_pos();
+        writer.write_u64(word_pos as u64);
+        writer.write_u64((word_pos >> 64) as u64);
+
+        match &self.drift {
+            Some(tracker) => {
+                writer.write_bytes(&[1]);
+                tracker.write(&mut writer);
+            }
+            None => writer.write_bytes(&[0]),
+        }
+
+        let checksum = crc32(writer.bytes());
+        writer.write_u32(checksum);
+        writer.into_bytes()
+    }
+
+    pub fn from_checkpoint(bytes: &[u8]) -> Result<Simulation, CheckpointError> {
+        let mut reader = ByteReader::new(bytes);
+
+        if reader.read_bytes(MAGIC.len()) != Some(&MAGIC[..]) {
+            return Err(CheckpointError::BadMagic);
+        }
+        let version = reader.read_u32().ok_or(CheckpointError::Truncated)?;
+        if version != CHECKPOINT_VERSION {
+            return Err(CheckpointError::UnsupportedVersion(version));
+        }
+
+        let body_len = bytes
+            .len()
+            .checked_sub(4)
+            .ok_or(CheckpointError::Truncated)?;
+        let (body, checksum) = bytes.split_at(body_len);
+        if crc32(body) != u32::from_le_bytes(checksum.try_into().unwrap()) {
+            return Err(CheckpointError::ChecksumMismatch);
+        }
+
+        let mut reader = ByteReader::new(body);
+        reader.read_bytes(MAGIC.len() + 4);
+        let snapshot_len = reader
+            .read_u64()
+            .and_then(|len| usize::try_from(len).ok())
+            .ok_or(CheckpointError::Truncated)?;
+        let snapshot = reader
+            .read_bytes(snapshot_len)
+            .ok_or(CheckpointError::Truncated)?;
+        let mut sim = Simulation::from_bytes(snapshot).map_err(CheckpointError::Snapshot)?;
+
+        let (rng, drift) = read_run_state(&mut reader).ok_or(CheckpointError::Truncated)?;
+        if reader.remaining() > 0 {
+            return Err(CheckpointError::TrailingBytes);
+        }
+        sim.rng = rng;
+        sim.drift = drift;
+        Ok(sim)
+    }
+}
+
+/// The RNG and drift tracker following the snapshot, `None` when they run
+/// out or don't add up.
+fn read_run_state(reader: &mut ByteReader) -> Option<(ChaCha8Rng, Option<DriftTracker>)> {
+    let seed: [u8; 32] = reader.read_bytes(32)?.try_into().ok()?;
+    let mut rng = ChaCha8Rng::from_seed(seed);
+    rng.set_stream(reader.read_u64()?);
+    let word_pos = reader.read_u64()? as u128 | (reader.read_u64()? as u128) << 64;
+    rng.set_word_pos(word_pos);
+
+    let drift = match reader.read_bytes(1)?[0] {
+        0 => None,
+        1 => Some(DriftTracker::read(reader)?),
+        _ => return None,
+    };
+    Some((rng, drift))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::PotentialMethod;
+    use crate::particle::Particle;
+    use crate::vector2::Vector2;
+
+    fn cluster() -> Simulation {
+        let mut sim = Simulation::new(
+            (0..40)
+                .map(|i| {
+                    let angle = i as f32 * 0.7;
+                    let radius = 5.0 + i as f32;
+                    Particle::new(
+                        1.0 + (i % 3) as f32,
+                        1.0,
+                        Vector2::new(100.0 + radius * angle.cos(), 100.0 + radius * angle.sin()),
+                        Vector2::new(-angle.sin(), angle.cos()),
+                        [255.0, 255.0, 255.0],
+                    )
+                })
+                .collect(),
+        );
+        // Both runs need the same IDs, which are random by default
+        sim.set_ids((0..40).collect());
+        sim.seed(7);
+        sim.track_drift(3, 16, PotentialMethod::Tree);
+        sim
+    }
+
+    /// Steps and draws force accuracy samples, which consume the RNG.
+    fn run(sim: &mut Simulation, steps: usize) -> Vec<f32> {
+        (0..steps)
+            .map(|_| {
+                sim.step(Vector2::new(200.0, 200.0), 1.0, 0.5, 0.01);
+                sim.force_accuracy(10, 1.0, 0.5, sim.theta).mean_error
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_restart_is_bitwise_identical() {
+        let mut uninterrupted = cluster();
+        run(&mut uninterrupted, 20);
+        let expected_errors = run(&mut uninterrupted, 25);
+
+        let mut interrupted = cluster();
+        run(&mut interrupted, 20);
+        let checkpoint = interrupted.to_checkpoint();
+        drop(interrupted);
+        let mut restarted = Simulation::from_checkpoint(&checkpoint).unwrap();
+        let errors = run(&mut restarted, 25);
+
+        assert_eq!(errors, expected_errors);
+        assert_eq!(restarted.to_checkpoint(), uninterrupted.to_checkpoint());
+        assert_eq!(
+            restarted.drift.unwrap().samples(),
+            uninterrupted.drift.unwrap().samples()
+        );
+    }
+
+    #[test]
+    fn test_corrupt_checkpoints_are_rejected() {
+        let bytes = cluster().to_checkpoint();
+
+        let mut flipped = bytes.clone();
+        flipped[100] ^= 1;
+        assert_eq!(
+            Simulation::from_checkpoint(&flipped).unwrap_err(),
+            CheckpointError::ChecksumMismatch
+        );
+        assert_eq!(
+            Simulation::from_checkpoint(&cluster().to_bytes()).unwrap_err(),
+            CheckpointError::BadMagic
+        );
+
+        // Valid checksum over a body that ends early
+        let mut short = bytes[..bytes.len() - 12].to_vec();
+        let checksum = crc32(&short);
+        short.extend_from_slice(&checksum.to_le_bytes());
+        assert_eq!(
+            Simulation::from_checkpoint(&short).unwrap_err(),
+            CheckpointError::Truncated
+        );
+    }
+}
@@ -0,0 +1,365 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::particle::Particle;
+use crate::simulation::Simulation;
+use crate::vector2::Vector2;
+
+const HEADER: &str = "id,x,y,vx,vy,mass,diameter,r,g,b";
+
+/// Particle state a CSV column can hold, in the order written on export.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CsvField {
+    Id,
+    X,
+    Y,
+    Vx,
+    Vy,
+    Mass,
+    Diameter,
+    R,
+    G,
+    B,
+}
+
+const FIELDS: [CsvField; 10] = [
+    CsvField::Id,
+    CsvField::X,
+    CsvField::Y,
+    CsvField::Vx,
+    CsvField::Vy,
+    CsvField::Mass,
+    CsvField::Diameter,
+    CsvField::R,
+    CsvField::G,
+    CsvField::B,
+];
+
+impl CsvField {
+    /// Recognizes the export names and the spellings pandas and
+    /// spreadsheets commonly use, ignoring case.
+    pub fn from_name(name: &str) -> Option<CsvField> {
+        let name = name.trim().trim_matches('"').to_ascii_lowercase();
+        let field = match name.as_str() {
+            "id" => CsvField::Id,
+            "x" | "pos_x" | "position_x" | "positions_x" => CsvField::X,
+            "y" | "pos_y" | "position_y" | "positions_y" => CsvField::Y,
+            "vx" | "vel_x" | "velocity_x" | "velocities_x" => CsvField::Vx,
+            "vy" | "vel_y" | "velocity_y" | "velocities_y" => CsvField::Vy,
+            "m" | "mass" | "masses" => CsvField::Mass,
+            "d" | "diameter" | "diameters" => CsvField::Diameter,
+            "r" | "red" | "color_r" => CsvField::R,
+            "g" | "green" | "color_g" => CsvField::G,
+            "b" | "blue" | "color_b" => CsvField::B,
+            _ => return None,
+        };
+        Some(field)
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            CsvField::Id => "id",
+            CsvField::X => "x",
+            CsvField::Y => "y",
+            CsvField::Vx => "vx",
+            CsvField::Vy => "vy",
+            CsvField::Mass => "mass",
+            CsvField::Diameter => "diameter",
+            CsvField::R => "r",
+            CsvField::G => "g",
+            CsvField::B => "b",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CsvError {
+    UnknownColumn(String),
+    MissingColumn(&'static str),
+    /// 1-based line and column of a value that is absent or not a number
+    InvalidValue {
+        line: usize,
+        column: usize,
+    },
+    /// A row whose values a simulation can't run with, see
+    /// `Particle::validate`
+    InvalidParticle {
+        line: usize,
+        reason: &'static str,
+    },
+    DuplicateId(i32),
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CsvError::UnknownColumn(name) => write!(f, "Unknown column \"{}\"", name),
+            CsvError::MissingColumn(name) => write!(f, "Missing column \"{}\"", name),
+            CsvError::InvalidValue { line, column } => {
+                write!(f, "Invalid value at line {}, column {}", line, column)
+            }
+            CsvError::InvalidParticle { line, reason } => {
+                write!(f, "Invalid particle at line {}: {}", line, reason)
+            }
+            CsvError::DuplicateId(id) => write!(f, "Duplicate particle ID {}", id),
+        }
+    }
+}
+
+fn split_row(line: &str) -> Vec<&str> {
+    line.split(',')
+        .map(|value| value.trim().trim_matches('"'))
+        .collect()
+}
+
+fn is_number(value: &str) -> bool {
+    value.parse::<f64>().is_ok()
+}
+
+/// A row of column names: every cell a field name, or empty for a skipped
+/// column. A data row with a typo or a missing value is never mistaken for
+/// one.
+fn is_header(row: &[&str]) -> bool {
+    row.iter().any(|value| !value.is_empty())
+        && row
+            .iter()
+            .all(|value| value.is_empty() || CsvField::from_name(value).is_some())
+}
+
+/// Maps column names to fields. Columns with an empty name are skipped.
+pub fn columns_from_names<S: AsRef<str>>(names: &[S]) -> Result<Vec<Option<CsvField>>, CsvError> {
+    names
+        .iter()
+        .map(|name| {
+            let name = name.as_ref();
+            if name.trim().is_empty() {
+                return Ok(None);
+            }
+            CsvField::from_name(name)
+                .map(Some)
+                .ok_or_else(|| CsvError::UnknownColumn(name.to_string()))
+        })
+        .collect()
+}
+
+impl Simulation {
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(HEADER);
+        csv.push('\n');
+
+        for i in 0..self.count {
+            let values = FIELDS.map(|field| match field {
+                CsvField::Id => self.ids[i].to_string(),
+                CsvField::X => self.positions_x[i].to_string(),
+                CsvField::Y => self.positions_y[i].to_string(),
+                CsvField::Vx => self.velocities_x[i].to_string(),
+                CsvField::Vy => self.velocities_y[i].to_string(),
+                CsvField::Mass => self.masses[i].to_string(),
+                CsvField::Diameter => self.diameters[i].to_string(),
+                CsvField::R => self.colors[i * 3].to_string(),
+                CsvField::G => self.colors[i * 3 + 1].to_string(),
+                CsvField::B => self.colors[i * 3 + 2].to_string(),
+            });
+            csv.push_str(&values.join(","));
+            csv.push('\n');
+        }
+
+        csv
+    }
+
+    /// Reads particles from CSV. `columns` assigns a field to every column;
+    /// without it a header row is detected and mapped by name, and a file
+    /// without a header is read in export order. Only `x` and `y` are
+    /// required: velocities default to zero, mass and diameter to one, colors
+    /// to white and IDs to unique values. Particles are checked as scene
+    /// imports check them.
+    pub fn from_csv(
+        text: &str,
+        columns: Option<Vec<Option<CsvField>>>,
+    ) -> Result<Simulation, CsvError> {
+        let mut rows = text
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+            .peekable();
+
+        let columns = match columns {
+            Some(columns) => {
+                // A header is still skipped when the caller maps the columns
+                if rows
+                    .peek()
+                    .is_some_and(|(_, line)| is_header(&split_row(line)))
+                {
+                    rows.next();
+                }
+                columns
+            }
+            // A first row without numbers can only be names, so unknown
+            // ones are reported rather than read as data
+            None => match rows.peek().map(|(_, line)| split_row(line)) {
+                Some(names) if !names.iter().any(|value| is_number(value)) => {
+                    rows.next();
+                    columns_from_names(&names)?
+                }
+                _ => FIELDS.iter().copied().map(Some).collect(),
+            },
+        };
+
+        for required in [CsvField::X, CsvField::Y] {
+            if !columns.contains(&Some(required)) {
+                return Err(CsvError::MissingColumn(required.name()));
+            }
+        }
+
+        let mut particles = Vec::new();
+        let mut ids = HashSet::new();
+        for (line, text) in rows {
+            let values = split_row(text);
+            let mut particle = Particle::new(
+                1.0,
+                1.0,
+                Vector2::new(0.0, 0.0),
+                Vector2::new(0.0, 0.0),
+                [255.0, 255.0, 255.0],
+            );
+
+            for (column, field) in columns.iter().enumerate() {
+                let Some(field) = field else { continue };
+                let invalid = CsvError::InvalidValue {
+                    line,
+                    column: column + 1,
+                };
+                let value = values.get(column).ok_or(invalid.clone())?;
+
+                if *field == CsvField::Id {
+                    particle.id = value.parse().map_err(|_| invalid)?;
+                    continue;
+                }
+
+                let value: f32 = value.parse().map_err(|_| invalid)?;
+                match field {
+                    CsvField::Id => {}
+                    CsvField::X => particle.position.x = value,
+                    CsvField::Y => particle.position.y = value,
+                    CsvField::Vx => particle.velocity.x = value,
+                    CsvField::Vy => particle.velocity.y = value,
+                    CsvField::Mass => particle.mass = value,
+                    CsvField::Diameter => particle.diameter = value,
+                    CsvField::R => particle.color_r = value,
+                    CsvField::G => particle.color_g = value,
+                    CsvField::B => particle.color_b = value,
+                }
+            }
+
+            particle
+                .validate()
+                .map_err(|reason| CsvError::InvalidParticle { line, reason })?;
+            if columns.contains(&Some(CsvField::Id)) && !ids.insert(particle.id) {
+                return Err(CsvError::DuplicateId(particle.id));
+            }
+            particles.push(particle);
+        }
+
+        Ok(Simulation::new(particles))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let sim = Simulation::new(vec![Particle::new(
+            2.5,
+            0.75,
+            Vector2::new(1.0, -2.0),
+            Vector2::new(0.125, 3.0),
+            [10.0, 20.0, 30.0],
+        )]);
+
+        let csv = sim.to_csv();
+        assert!(csv.starts_with("id,x,y,vx,vy,mass,diameter,r,g,b\n"));
+
+        let restored = Simulation::from_csv(&csv, None).unwrap();
+        assert_eq!(restored.ids, sim.ids);
+        assert_eq!(restored.positions_y, vec![-2.0]);
+        assert_eq!(restored.velocities_x, vec![0.125]);
+        assert_eq!(restored.masses, vec![2.5]);
+        assert_eq!(restored.diameters, vec![0.75]);
+        assert_eq!(restored.colors, vec![10.0, 20.0, 30.0]);
+    }
+
+    #[test]
+    fn test_header_mapping_and_defaults() {
+        let csv = "# from pandas\nMass, pos_x, pos_y, note\n3, 1, 2, 0\n\n4, 5, 6, 0\n";
+
+        let sim = Simulation::from_csv(csv, None);
+        assert_eq!(
+            sim.unwrap_err(),
+            CsvError::UnknownColumn("note".to_string())
+        );
+
+        let csv = csv.replace("note", "");
+        let sim = Simulation::from_csv(&csv, None).unwrap();
+        assert_eq!(sim.count, 2);
+        assert_eq!(sim.masses, vec![3.0, 4.0]);
+        assert_eq!(sim.positions_x, vec![1.0, 5.0]);
+        assert_eq!(sim.velocities_y, vec![0.0, 0.0]);
+        assert_eq!(sim.diameters, vec![1.0, 1.0]);
+    }
+
+    #[test]
+    fn test_explicit_columns() {
+        let columns = columns_from_names(&["y", "", "x"]).unwrap();
+
+        let sim = Simulation::from_csv("1,9,2\n3,9,4\n", Some(columns)).unwrap();
+
+        assert_eq!(sim.positions_x, vec![2.0, 4.0]);
+        assert_eq!(sim.positions_y, vec![1.0, 3.0]);
+
+        let columns = columns_from_names(&["x", "y"]).unwrap();
+        let with_header = Simulation::from_csv("pos_x,\"Y\"\n1,2\n", Some(columns.clone()));
+        assert_eq!(with_header.unwrap().count, 1);
+        // A bad first row is an error, not a header to skip
+        assert_eq!(
+            Simulation::from_csv("1,abc\n3,4\n", Some(columns)).unwrap_err(),
+            CsvError::InvalidValue { line: 1, column: 2 }
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            Simulation::from_csv("mass\n1\n", None).unwrap_err(),
+            CsvError::MissingColumn("x")
+        );
+        assert_eq!(
+            Simulation::from_csv("x,y\n1,2\n3\n", None).unwrap_err(),
+            CsvError::InvalidValue { line: 3, column: 2 }
+        );
+        assert_eq!(
+            Simulation::from_csv("1,2,,4,5,1,1,0,0,0\n", None).unwrap_err(),
+            CsvError::InvalidValue { line: 1, column: 3 }
+        );
+    }
+
+    #[test]
+    fn test_particles_are_validated() {
+        for mass in ["-5", "NaN"] {
+            assert_eq!(
+                Simulation::from_csv(&format!("x,y,mass\n1,2,1\n3,4,{}\n", mass), None)
+                    .unwrap_err(),
+                CsvError::InvalidParticle {
+                    line: 3,
+                    reason: "mass must be non-negative"
+                }
+            );
+        }
+        assert_eq!(
+            Simulation::from_csv("id,x,y\n7,1,2\n7,3,4\n", None).unwrap_err(),
+            CsvError::DuplicateId(7)
+        );
+    }
+}
@@ -0,0 +1,187 @@
+use std::f32::consts::PI;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::simulation::Simulation;
+use crate::vector2::Vector2;
+
+/// How a particle's local density is estimated from its `k` nearest
+/// neighbors, at distance `h_k` for the farthest of them.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DensityEstimator {
+    /// Neighbor mass over the disc of radius `h_k`
+    NearestNeighbor,
+    /// SPH sum with a 2D cubic spline kernel reaching out to `h_k`
+    Kernel,
+}
+
+/// 2D cubic spline (Monaghan 1992) with smoothing length `h`, vanishing
+/// beyond `2h`.
+fn cubic_spline(r: f32, h: f32) -> f32 {
+    let sigma = 10.0 / (7.0 * PI * h * h);
+    let q = r / h;
+
+    if q < 1.0 {
+        sigma * (1.0 - 1.5 * q * q + 0.75 * q * q * q)
+    } else if q < 2.0 {
+        sigma * 0.25 * (2.0 - q).powi(3)
+    } else {
+        0.0
+    }
+}
+
+impl Simulation {
+    /// Fills `densities` with a mass-per-area estimate for every particle.
+    /// The values are not updated by `step`.
+    pub fn estimate_densities(&mut self, k: usize, estimator: DensityEstimator) {
+        let tree = self.tree();
+        // Coincident neighbors would leave no area to spread their mass over;
+        // the smallest distance f32 resolves across the whole system stands in
+        let min_h = tree.boundary.width * f32::EPSILON;
+
+        let densities: Vec<f32> = (0..self.count)
+            .map(|i| {
+                let p_pos = Vector2::new(self.positions_x[i], self.positions_y[i]);
+                let neighbors =
+                    tree.nearest_neighbors(p_pos, k, Some(i), &self.positions_x, &self.positions_y);
+
+                let farthest = match neighbors.last() {
+                    Some(&j) => j,
+                    None => return 0.0,
+                };
+                let h_k = p_pos
+                    .distance(&Vector2::new(
+                        self.positions_x[farthest],
+                        self.positions_y[farthest],
+                    ))
+                    .max(min_h);
+
+                match estimator {
+                    DensityEstimator::NearestNeighbor => {
+                        let mass: f32 = neighbors.iter().map(|&j| self.masses[j]).sum();
+                        mass / (PI * h_k * h_k)
+                    }
+                    DensityEstimator::Kernel => {
+                        let h = h_k / 2.0;
+                        neighbors.iter().fold(
+                            self.masses[i] * cubic_spline(0.0, h),
+                            |density, &j| {
+                                let other_pos =
+                                    Vector2::new(self.positions_x[j], self.positions_y[j]);
+                                density
+                                    + self.masses[j] * cubic_spline(p_pos.distance(&other_pos), h)
+                            },
+                        )
+                    }
+                }
+            })
+            .collect();
+
+        self.densities = densities;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::particle::Particle;
+
+    /// Unit-mass particles on a grid, with spacing 1 left of x = 20 and
+    /// spacing 2 to the right of it.
+    fn two_lattices() -> Simulation {
+        let mut particles = Vec::new();
+        for i in 0..20 {
+            for j in 0..40 {
+                particles.push((i as f32, j as f32));
+            }
+        }
+        for i in 0..10 {
+            for j in 0..20 {
+                particles.push((22.0 + 2.0 * i as f32, 2.0 * j as f32));
+            }
+        }
+
+        Simulation::new(
+            particles
+                .into_iter()
+                .map(|(x, y)| {
+                    Particle::new(
+                        1.0,
+                        1.0,
+                        Vector2::new(x, y),
+                        Vector2::new(0.0, 0.0),
+                        [255.0, 255.0, 255.0],
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_kernel_recovers_lattice_density() {
+        let mut sim = two_lattices();
+
+        sim.estimate_densities(32, DensityEstimator::Kernel);
+
+        // Interior particles: (10, 20) on the fine grid, (30, 20) on the coarse one
+        let fine = sim.densities[10 * 40 + 20];
+        let coarse = sim.densities[800 + 4 * 20 + 10];
+        assert!((fine - 1.0).abs() < 0.1, "fine density {}", fine);
+        assert!((coarse - 0.25).abs() < 0.025, "coarse density {}", coarse);
+    }
+
+    #[test]
+    fn test_nearest_neighbor_density_scales_with_spacing() {
+        let mut sim = two_lattices();
+
+        sim.estimate_densities(8, DensityEstimator::NearestNeighbor);
+
+        let fine = sim.densities[10 * 40 + 20];
+        let coarse = sim.densities[800 + 4 * 20 + 10];
+        assert!((fine / coarse - 4.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_coincident_particles_have_finite_density() {
+        let particle = |x: f32| {
+            Particle::new(
+                1.0,
+                1.0,
+                Vector2::new(x, 0.0),
+                Vector2::new(0.0, 0.0),
+                [255.0, 255.0, 255.0],
+            )
+        };
+        let mut sim = Simulation::new(vec![
+            particle(0.0),
+            particle(0.0),
+            particle(0.0),
+            particle(50.0),
+        ]);
+
+        for estimator in [DensityEstimator::NearestNeighbor, DensityEstimator::Kernel] {
+            sim.estimate_densities(2, estimator);
+
+            let (stacked, apart) = (sim.densities[0], sim.densities[3]);
+            assert!(stacked.is_finite(), "{:?} density {}", estimator, stacked);
+            assert!(stacked > apart && apart > 0.0);
+        }
+    }
+
+    #[test]
+    fn test_lone_particle_has_zero_density() {
+        let mut sim = Simulation::new(vec![Particle::new(
+            1.0,
+            1.0,
+            Vector2::new(0.0, 0.0),
+            Vector2::new(0.0, 0.0),
+            [255.0, 255.0, 255.0],
+        )]);
+
+        sim.estimate_densities(8, DensityEstimator::Kernel);
+
+        assert_eq!(sim.densities, vec![0.0]);
+    }
+}
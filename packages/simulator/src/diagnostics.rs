@@ -0,0 +1,201 @@
+use serde::Serialize;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::simulation::Simulation;
+use crate::utils::calculation_utils::potential_energy;
+use crate::vector2::Vector2;
+
+/// How the potential energy is summed: exactly over all pairs in O(n^2), or
+/// with the Barnes-Hut approximation in O(n log n).
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PotentialMethod {
+    Direct,
+    Tree,
+}
+
+/// Conserved quantities of the whole system. Accumulated in f64 so that
+/// drift stays visible above rounding noise.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+pub struct Diagnostics {
+    pub kinetic_energy: f64,
+    pub potential_energy: f64,
+    pub total_energy: f64,
+    pub momentum_x: f64,
+    pub momentum_y: f64,
+    /// About the center of mass, positive counterclockwise in world coordinates
+    pub angular_momentum: f64,
+    /// `K / |W|`, 0.5 in virial equilibrium
+    pub virial_ratio: f64,
+}
+
+impl Simulation {
+    pub fn center_of_mass(&self) -> (Vector2, Vector2) {
+        let mut mass = 0.0;
+        let mut position = (0.0, 0.0);
+        let mut velocity = (0.0, 0.0);
+
+        for i in 0..self.count {
+            let m = self.masses[i] as f64;
+            mass += m;
+            position.0 += m * self.positions_x[i] as f64;
+            position.1 += m * self.positions_y[i] as f64;
+            velocity.0 += m * self.velocities_x[i] as f64;
+            velocity.1 += m * self.velocities_y[i] as f64;
+        }
+
+        if mass == 0.0 {
+            return (Vector2::new(0.0, 0.0), Vector2::new(0.0, 0.0));
+        }
+
+        (
+            Vector2::new((position.0 / mass) as f32, (position.1 / mass) as f32),
+            Vector2::new((velocity.0 / mass) as f32, (velocity.1 / mass) as f32),
+        )
+    }
+
+    pub fn potential_energy(&self, gravity: f32, epsilon: f32, method: PotentialMethod) -> f64 {
+        match method {
+            PotentialMethod::Direct => potential_energy(
+                &self.positions_x,
+                &self.positions_y,
+                &self.masses,
+                gravity,
+                epsilon,
+            ),
+            PotentialMethod::Tree => {
+                let tree = self.tree();
+                let sum: f64 = (0..self.count)
+                    .map(|i| {
+                        let p_pos = Vector2::new(self.positions_x[i], self.positions_y[i]);
+                        let potential = tree.compute_potential(
+                            p_pos,
+                            gravity,
+                            epsilon,
+                            self.theta,
+                            &self.positions_x,
+                            &self.positions_y,
+                            &self.masses,
+                        );
+                        self.masses[i] as f64 * potential
+                    })
+                    .sum();
+                // Every pair was counted from both ends
+                sum / 2.0
+            }
+        }
+    }
+
+    /// Conserved quantities under the gravity and softening in `params`, as
+    /// recorded by the last `step` or set by `SimulationBuilder`.
+    pub fn diagnostics(&self, method: PotentialMethod) -> Diagnostics {
+        let (center, center_velocity) = self.center_of_mass();
+
+        let mut kinetic_energy = 0.0;
+        let mut momentum_x = 0.0;
+        let mut momentum_y = 0.0;
+        let mut angular_momentum = 0.0;
+
+        for i in 0..self.count {
+            let m = self.masses[i] as f64;
+            let vx = self.velocities_x[i] as f64;
+            let vy = self.velocities_y[i] as f64;
+
+            kinetic_energy += 0.5 * m * (vx * vx + vy * vy);
+            momentum_x += m * vx;
+            momentum_y += m * vy;
+
+            let rx = (self.positions_x[i] - center.x) as f64;
+            let ry = (self.positions_y[i] - center.y) as f64;
+            let ux = vx - center_velocity.x as f64;
+            let uy = vy - center_velocity.y as f64;
+            angular_momentum += m * (rx * uy - ry * ux);
+        }
+
+        let potential_energy =
+            self.potential_energy(self.params.gravity, self.params.epsilon, method);
+        let virial_ratio = if potential_energy != 0.0 {
+            kinetic_energy / potential_energy.abs()
+        } else {
+            0.0
+        };
+
+        Diagnostics {
+            kinetic_energy,
+            potential_energy,
+            total_energy: kinetic_energy + potential_energy,
+            momentum_x,
+            momentum_y,
+            angular_momentum,
+            virial_ratio,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::particle::Particle;
+
+    fn body(position: Vector2, velocity: Vector2, mass: f32) -> Particle {
+        Particle::new(mass, 1.0, position, velocity, [255.0, 255.0, 255.0])
+    }
+
+    #[test]
+    fn test_two_body_diagnostics() {
+        let mut sim = Simulation::new(vec![
+            body(Vector2::new(0.0, 0.0), Vector2::new(0.0, -1.0), 1.0),
+            body(Vector2::new(2.0, 0.0), Vector2::new(0.0, 1.0), 1.0),
+        ]);
+        sim.params.gravity = 1.0;
+
+        let d = sim.diagnostics(PotentialMethod::Direct);
+
+        assert_eq!(d.kinetic_energy, 1.0);
+        assert_eq!(d.potential_energy, -0.5);
+        assert_eq!(d.total_energy, 0.5);
+        assert_eq!(d.momentum_x, 0.0);
+        assert_eq!(d.momentum_y, 0.0);
+        // Each body: r = 1, v = 1 about the center of mass
+        assert_eq!(d.angular_momentum, 2.0);
+        assert_eq!(d.virial_ratio, 2.0);
+    }
+
+    #[test]
+    fn test_tree_potential_close_to_direct() {
+        let particles: Vec<Particle> = (0..200)
+            .map(|i| {
+                let angle = i as f32 * 2.399_963; // golden angle spreads points evenly
+                let radius = (i as f32).sqrt() * 5.0;
+                body(
+                    Vector2::new(500.0 + radius * angle.cos(), 500.0 + radius * angle.sin()),
+                    Vector2::new(0.0, 0.0),
+                    1.0 + (i % 3) as f32,
+                )
+            })
+            .collect();
+        let sim = Simulation::new(particles);
+
+        let direct = sim.potential_energy(1.0, 1.0, PotentialMethod::Direct);
+        let tree = sim.potential_energy(1.0, 1.0, PotentialMethod::Tree);
+
+        assert!(((tree - direct) / direct).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_coincident_pair_has_no_potential_with_either_method() {
+        let sim = Simulation::new(vec![
+            body(Vector2::new(0.0, 0.0), Vector2::new(0.0, 0.0), 1.0),
+            body(Vector2::new(0.0, 0.0), Vector2::new(0.0, 0.0), 2.0),
+            body(Vector2::new(3.0, 4.0), Vector2::new(0.0, 0.0), 1.0),
+        ]);
+
+        let direct = sim.potential_energy(1.0, 0.0, PotentialMethod::Direct);
+        let tree = sim.potential_energy(1.0, 0.0, PotentialMethod::Tree);
+
+        // Only the two pairs with the distant body, both 5 apart
+        assert!((direct + 3.0 / 5.0).abs() < 1e-9);
+        assert!((tree - direct).abs() < 1e-6);
+    }
+}
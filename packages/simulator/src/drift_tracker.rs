@@ -0,0 +1,200 @@
+use crate::diagnostics::{Diagnostics, PotentialMethod};
+use crate::utils::binary_utils::{ByteReader, ByteWriter};
+
+/// Bounded history of the relative energy and angular momentum error since
+/// the first sample. The buffers are allocated once at full capacity and
+/// written as a ring, so pointers handed to JS stay valid while tracking.
+#[derive(Debug, Clone)]
+pub struct DriftTracker {
+    pub interval: u64,
+    pub method: PotentialMethod,
+    initial_energy: Option<f64>,
+    initial_angular_momentum: f64,
+    pub times: Vec<f64>,
+    pub energy_errors: Vec<f32>,
+    pub angular_momentum_errors: Vec<f32>,
+    /// Slot the next sample is written to
+    head: usize,
+    len: usize,
+}
+
+impl DriftTracker {
+    pub fn new(interval: u64, capacity: usize, method: PotentialMethod) -> DriftTracker {
+        let capacity = capacity.max(1);
+
+        DriftTracker {
+            interval: interval.max(1),
+            method,
+            initial_energy: None,
+            initial_angular_momentum: 0.0,
+            times: vec![0.0; capacity],
+            energy_errors: vec![0.0; capacity],
+            angular_momentum_errors: vec![0.0; capacity],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    pub fn has_baseline(&self) -> bool {
+        self.initial_energy.is_some()
+    }
+
+    pub fn set_baseline(&mut self, diagnostics: &Diagnostics) {
+        self.initial_energy = Some(diagnostics.total_energy);
+        self.initial_angular_momentum = diagnostics.angular_momentum;
+    }
+
+    /// Makes the next sample the new baseline, keeping recorded samples.
+    pub fn clear_baseline(&mut self) {
+        self.initial_energy = None;
+    }
+
+    pub fn is_due(&self, step_count: u64) -> bool {
+        step_count.is_multiple_of(self.interval)
+    }
+
+    pub fn record(&mut self, time: f64, diagnostics: &Diagnostics) {
+        let initial_energy = match self.initial_energy {
+            Some(energy) => energy,
+            None => {
+                self.set_baseline(diagnostics);
+                diagnostics.total_energy
+            }
+        };
+
+        self.times[self.head] = time;
+        self.energy_errors[self.head] =
+            relative_error(diagnostics.total_energy, initial_energy) as f32;
+        self.angular_momentum_errors[self.head] =
+            relative_error(diagnostics.angular_momentum, self.initial_angular_momentum) as f32;
+
+        self.head = (self.head + 1) % self.capacity();
+        self.len = (self.len + 1).min(self.capacity());
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.times.len()
+    }
+
+    /// Number of valid samples, at most `capacity`.
+    pub fn sample_count(&self) -> usize {
+        self.len
+    }
+
+    /// Slot holding the oldest sample; samples run from here, wrapping around.
+    pub fn start(&self) -> usize {
+        if self.len < self.capacity() {
+            0
+        } else {
+            self.head
+        }
+    }
+
+    /// Samples oldest first as `(time, energy error, angular momentum error)`.
+    pub fn samples(&self) -> Vec<(f64, f32, f32)> {
+        (0..self.len)
+            .map(|i| (self.start() + i) % self.capacity())
+            .map(|slot| {
+                (
+                    self.times[slot],
+                    self.energy_errors[slot],
+                    self.angular_momentum_errors[slot],
+                )
+            })
+            .collect()
+    }
+
+    /// Complete state, baseline and ring position included, for checkpoints.
+    pub(crate) fn write(&self, writer: &mut ByteWriter) {
+        writer.write_u64(self.interval);
+        writer.write_bytes(&[self.method as u8, self.initial_energy.is_some() as u8]);
+        writer.write_f64(self.initial_energy.unwrap_or(0.0));
+        writer.write_f64(self.initial_angular_momentum);
+        writer.write_u64(self.capacity() as u64);
+        writer.write_f64_slice(&self.times);
+        writer.write_f32_slice(&self.energy_errors);
+        writer.write_f32_slice(&self.angular_momentum_errors);
+        writer.write_u64(self.head as u64);
+        writer.write_u64(self.len as u64);
+    }
+
+    /// Reads what `write` wrote, `None` when it runs out or doesn't add up.
+    pub(crate) fn read(reader: &mut ByteReader) -> Option<DriftTracker> {
+        let interval = reader.read_u64()?;
+        let flags = reader.read_bytes(2)?;
+        let method = match flags[0] {
+            0 => PotentialMethod::Direct,
+            1 => PotentialMethod::Tree,
+            _ => return None,
+        };
+        let initial_energy = reader.read_f64()?;
+        let initial_angular_momentum = reader.read_f64()?;
+        let capacity = usize::try_from(reader.read_u64()?).ok()?;
+
+        let tracker = DriftTracker {
+            interval,
+            method,
+            initial_energy: (flags[1] != 0).then_some(initial_energy),
+            initial_angular_momentum,
+            times: reader.read_f64_vec(capacity)?,
+            energy_errors: reader.read_f32_vec(capacity)?,
+            angular_momentum_errors: reader.read_f32_vec(capacity)?,
+            head: usize::try_from(reader.read_u64()?).ok()?,
+            len: usize::try_from(reader.read_u64()?).ok()?,
+        };
+        if interval == 0 || tracker.head >= capacity || tracker.len > capacity {
+            return None;
+        }
+        Some(tracker)
+    }
+}
+
+/// Error relative to the initial value, absolute when that value is zero.
+fn relative_error(value: f64, initial: f64) -> f64 {
+    if initial == 0.0 {
+        value
+    } else {
+        (value - initial) / initial.abs()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diagnostics(total_energy: f64, angular_momentum: f64) -> Diagnostics {
+        Diagnostics {
+            kinetic_energy: 0.0,
+            potential_energy: total_energy,
+            total_energy,
+            momentum_x: 0.0,
+            momentum_y: 0.0,
+            angular_momentum,
+            virial_ratio: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_relative_errors_against_first_sample() {
+        let mut tracker = DriftTracker::new(1, 4, PotentialMethod::Direct);
+
+        tracker.record(0.0, &diagnostics(-2.0, 4.0));
+        tracker.record(1.0, &diagnostics(-2.2, 5.0));
+
+        assert_eq!(tracker.samples(), vec![(0.0, 0.0, 0.0), (1.0, -0.1, 0.25)]);
+    }
+
+    #[test]
+    fn test_ring_buffer_keeps_latest_samples() {
+        let mut tracker = DriftTracker::new(1, 3, PotentialMethod::Direct);
+
+        for i in 0..5 {
+            tracker.record(i as f64, &diagnostics(-1.0, 1.0));
+        }
+
+        assert_eq!(tracker.sample_count(), 3);
+        assert_eq!(tracker.start(), 2);
+        let times: Vec<f64> = tracker.samples().iter().map(|s| s.0).collect();
+        assert_eq!(times, vec![2.0, 3.0, 4.0]);
+    }
+}
@@ -0,0 +1,216 @@
+use std::f32::consts::FRAC_PI_6;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::particle::Particle;
+use crate::vector2::Vector2;
+
+/// Mass of the test particle relative to the binary components.
+const TEST_PARTICLE_MASS_RATIO: f32 = 1.0e-6;
+
+/// Orbital radius of the test particle in binary separations, outside the
+/// region where circumbinary orbits are unstable.
+const TEST_PARTICLE_RADIUS: f32 = 3.0;
+
+const COLORS: [[f32; 3]; 3] = [
+    [255.0, 80.0, 80.0],
+    [80.0, 255.0, 80.0],
+    [80.0, 160.0, 255.0],
+];
+
+/// Canonical few-body configurations, usable both as demos and as
+/// regression fixtures for the integrator.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FewBodyProblem {
+    FigureEight,
+    LagrangeTriangle,
+    Pythagorean,
+    CircularBinary,
+}
+
+impl FewBodyProblem {
+    /// Bodies centered on `center` with lengths in units of `length` and masses
+    /// in units of `mass`. Velocities are scaled by `sqrt(G * mass / length)`
+    /// so every problem keeps its dimensionless dynamics.
+    pub fn particles(
+        &self,
+        center: Vector2,
+        length: f32,
+        mass: f32,
+        gravity: f32,
+        diameter: f32,
+    ) -> Vec<Particle> {
+        let velocity_unit = (gravity * mass / length).sqrt();
+
+        let bodies: Vec<(f32, Vector2, Vector2)> = match self {
+            // Chenciner & Montgomery (2000), initial conditions from Simó
+            FewBodyProblem::FigureEight => {
+                let position = Vector2::new(0.970_004_4, -0.243_087_5);
+                let velocity = Vector2::new(-0.932_407_4, -0.864_731_5);
+                vec![
+                    (1.0, position, velocity.scale(-0.5)),
+                    (1.0, position.scale(-1.0), velocity.scale(-0.5)),
+                    (1.0, Vector2::new(0.0, 0.0), velocity),
+                ]
+            }
+            // Equal masses on a rigidly rotating equilateral triangle of unit
+            // side, each moving at v = sqrt(G m / L)
+            FewBodyProblem::LagrangeTriangle => {
+                let radius = 1.0 / 3.0_f32.sqrt();
+                (0..3)
+                    .map(|i| {
+                        let angle = FRAC_PI_6 + i as f32 * 2.0 * std::f32::consts::FRAC_PI_3;
+                        let direction = Vector2::new(angle.cos(), angle.sin());
+                        (
+                            1.0,
+                            direction.scale(radius),
+                            Vector2::new(-direction.y, direction.x),
+                        )
+                    })
+                    .collect()
+            }
+            // Burrau's problem: masses 3, 4, 5 at rest on a 3-4-5 triangle
+            FewBodyProblem::Pythagorean => vec![
+                (3.0, Vector2::new(1.0, 3.0), Vector2::new(0.0, 0.0)),
+                (4.0, Vector2::new(-2.0, -1.0), Vector2::new(0.0, 0.0)),
+                (5.0, Vector2::new(1.0, -1.0), Vector2::new(0.0, 0.0)),
+            ],
+            // Equal-mass circular binary of unit separation with a massless
+            // test particle on a circular circumbinary orbit
+            FewBodyProblem::CircularBinary => {
+                let binary_speed = 0.5_f32.sqrt();
+                let test_speed = (2.0 / TEST_PARTICLE_RADIUS).sqrt();
+                vec![
+                    (
+                        1.0,
+                        Vector2::new(-0.5, 0.0),
+                        Vector2::new(0.0, -binary_speed),
+                    ),
+                    (1.0, Vector2::new(0.5, 0.0), Vector2::new(0.0, binary_speed)),
+                    (
+                        TEST_PARTICLE_MASS_RATIO,
+                        Vector2::new(TEST_PARTICLE_RADIUS, 0.0),
+                        Vector2::new(0.0, test_speed),
+                    ),
+                ]
+            }
+        };
+
+        bodies
+            .into_iter()
+            .zip(COLORS.iter())
+            .map(|((m, position, velocity), color)| {
+                Particle::new(
+                    m * mass,
+                    diameter,
+                    center + position.scale(length),
+                    velocity.scale(velocity_unit),
+                    *color,
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::Simulation;
+
+    /// Period of the figure-eight choreography in units where G = m = 1.
+    const FIGURE_EIGHT_PERIOD: f32 = 6.325_913;
+
+    const WORLD: Vector2 = Vector2 { x: 10.0, y: 10.0 };
+    const CENTER: Vector2 = Vector2 { x: 5.0, y: 5.0 };
+
+    fn momentum(particles: &[Particle]) -> Vector2 {
+        particles.iter().fold(Vector2::new(0.0, 0.0), |acc, p| {
+            acc + p.velocity.scale(p.mass)
+        })
+    }
+
+    #[test]
+    fn test_every_problem_starts_with_zero_momentum() {
+        for problem in [
+            FewBodyProblem::FigureEight,
+            FewBodyProblem::LagrangeTriangle,
+            FewBodyProblem::Pythagorean,
+            FewBodyProblem::CircularBinary,
+        ] {
+            let particles = problem.particles(CENTER, 2.0, 3.0, 0.5, 0.1);
+            assert_eq!(particles.len(), 3);
+            assert!(momentum(&particles).magnitude() < 1e-4, "{:?}", problem);
+        }
+    }
+
+    #[test]
+    fn test_figure_eight_returns_after_one_period() {
+        let particles = FewBodyProblem::FigureEight.particles(CENTER, 1.0, 1.0, 1.0, 0.1);
+        let mut sim = Simulation::new(particles);
+        let start_x = sim.positions_x.clone();
+        let start_y = sim.positions_y.clone();
+
+        let time_step = 1.0e-3;
+        let steps = (FIGURE_EIGHT_PERIOD / time_step).round() as usize;
+        for _ in 0..steps {
+            sim.step(WORLD, 1.0, 0.0, time_step);
+        }
+
+        for i in 0..3 {
+            let drift = Vector2::new(sim.positions_x[i], sim.positions_y[i])
+                .distance(&Vector2::new(start_x[i], start_y[i]));
+            assert!(drift < 0.1, "body {} drifted {}", i, drift);
+        }
+    }
+
+    #[test]
+    fn test_lagrange_triangle_stays_equilateral() {
+        let particles = FewBodyProblem::LagrangeTriangle.particles(CENTER, 1.0, 1.0, 1.0, 0.1);
+        let mut sim = Simulation::new(particles);
+
+        for _ in 0..1000 {
+            sim.step(WORLD, 1.0, 0.0, 1.0e-3);
+        }
+
+        let position = |i: usize| Vector2::new(sim.positions_x[i], sim.positions_y[i]);
+        for (a, b) in [(0, 1), (1, 2), (2, 0)] {
+            let side = position(a).distance(&position(b));
+            assert!((side - 1.0).abs() < 1e-2, "side {}-{} is {}", a, b, side);
+        }
+    }
+
+    #[test]
+    fn test_pythagorean_starts_at_rest_on_a_3_4_5_triangle() {
+        let particles = FewBodyProblem::Pythagorean.particles(CENTER, 2.0, 3.0, 1.0, 0.1);
+
+        let masses: Vec<f32> = particles.iter().map(|p| p.mass).collect();
+        assert_eq!(masses, vec![9.0, 12.0, 15.0]);
+        assert!(particles
+            .iter()
+            .all(|p| p.velocity == Vector2::new(0.0, 0.0)));
+
+        let total_mass: f32 = masses.iter().sum();
+        let center_of_mass = particles
+            .iter()
+            .fold(Vector2::new(0.0, 0.0), |acc, p| {
+                acc + p.position.scale(p.mass)
+            })
+            .scale(1.0 / total_mass);
+        assert!(center_of_mass.distance(&CENTER) < 1e-5);
+
+        // Each side is opposite the body whose mass matches its length
+        for (body, (a, b)) in [(0, (1, 2)), (1, (2, 0)), (2, (0, 1))] {
+            let side = particles[a].position.distance(&particles[b].position);
+            let expected = 2.0 * (body + 3) as f32;
+            assert!(
+                (side - expected).abs() < 1e-5,
+                "side {}-{} is {}",
+                a,
+                b,
+                side
+            );
+        }
+    }
+}
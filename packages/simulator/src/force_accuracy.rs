@@ -0,0 +1,146 @@
+use rand::seq::index::sample;
+use serde::Serialize;
+
+use crate::simulation::Simulation;
+use crate::utils::calculation_utils::softened_acceleration;
+use crate::vector2::Vector2;
+
+/// Relative error of the Barnes-Hut acceleration against direct summation,
+/// `|a_tree - a_direct| / |a_direct|`, over a random subset of particles.
+#[derive(Debug, Copy, Clone, Serialize)]
+pub struct ForceAccuracy {
+    pub samples: usize,
+    pub theta: f32,
+    pub mean_error: f32,
+    pub max_error: f32,
+    pub p99_error: f32,
+}
+
+impl Simulation {
+    /// Exact softened acceleration on particle `i` from every other particle.
+    pub fn direct_acceleration(&self, i: usize, gravity: f32, epsilon: f32) -> Vector2 {
+        let p_pos = Vector2::new(self.positions_x[i], self.positions_y[i]);
+
+        (0..self.count)
+            .filter(|&j| j != i)
+            .fold(Vector2::new(0.0, 0.0), |acc, j| {
+                let other_pos = Vector2::new(self.positions_x[j], self.positions_y[j]);
+                acc + softened_acceleration(p_pos, other_pos, self.masses[j], gravity, epsilon, 1.0)
+            })
+    }
+
+    /// Compares tree and direct accelerations for up to `sample_size`
+    /// particles drawn with `self.rng`, at opening angle `theta`. Particles
+    /// feeling no force are left out since their relative error is undefined.
+    pub fn force_accuracy(
+        &mut self,
+        sample_size: usize,
+        gravity: f32,
+        epsilon: f32,
+        theta: f32,
+    ) -> ForceAccuracy {
+        let indices = sample(&mut self.rng, self.count, sample_size.min(self.count));
+        let tree = self.tree();
+
+        let mut errors: Vec<f32> = indices
+            .iter()
+            .filter_map(|i| {
+                let p_pos = Vector2::new(self.positions_x[i], self.positions_y[i]);
+                let approximate = tree.compute_force(
+                    p_pos,
+                    gravity,
+                    epsilon,
+                    1.0,
+                    theta,
+                    &self.positions_x,
+                    &self.positions_y,
+                    &self.masses,
+                );
+                let exact = self.direct_acceleration(i, gravity, epsilon);
+
+                let magnitude = exact.magnitude();
+                if magnitude == 0.0 {
+                    return None;
+                }
+                Some((approximate - exact).magnitude() / magnitude)
+            })
+            .collect();
+
+        errors.sort_by(f32::total_cmp);
+
+        let samples = errors.len();
+        let (mean_error, max_error, p99_error) = if samples == 0 {
+            (0.0, 0.0, 0.0)
+        } else {
+            let mean = errors.iter().map(|&e| e as f64).sum::<f64>() / samples as f64;
+            let p99 = errors[(samples as f64 * 0.99).ceil() as usize - 1];
+            (mean as f32, errors[samples - 1], p99)
+        };
+
+        ForceAccuracy {
+            samples,
+            theta,
+            mean_error,
+            max_error,
+            p99_error,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::particle::Particle;
+    use rand::prelude::*;
+
+    fn random_cluster(count: usize) -> Simulation {
+        let mut rng = StdRng::seed_from_u64(11);
+        let particles = (0..count)
+            .map(|_| {
+                Particle::new(
+                    rng.gen_range(1.0..5.0),
+                    1.0,
+                    Vector2::new(rng.gen_range(0.0..1000.0), rng.gen_range(0.0..1000.0)),
+                    Vector2::new(0.0, 0.0),
+                    [255.0, 255.0, 255.0],
+                )
+            })
+            .collect();
+        Simulation::new(particles)
+    }
+
+    #[test]
+    fn test_zero_theta_matches_direct_sum() {
+        let mut sim = random_cluster(100);
+
+        let accuracy = sim.force_accuracy(100, 1.0, 1.0, 0.0);
+
+        assert_eq!(accuracy.samples, 100);
+        assert!(accuracy.max_error < 1e-4, "{:?}", accuracy);
+    }
+
+    #[test]
+    fn test_default_theta_is_accurate() {
+        let mut sim = random_cluster(500);
+
+        // Sampling every particle keeps the test deterministic
+        let accuracy = sim.force_accuracy(500, 1.0, 1.0, sim.theta);
+
+        // Net forces nearly cancel in a uniform field, so relative errors
+        // are larger than in a centrally concentrated scene
+        assert_eq!(accuracy.samples, 500);
+        assert!(accuracy.mean_error < 0.03, "{:?}", accuracy);
+        assert!(accuracy.p99_error < 0.25, "{:?}", accuracy);
+        assert!(accuracy.p99_error <= accuracy.max_error);
+    }
+
+    #[test]
+    fn test_error_grows_with_theta() {
+        let mut sim = random_cluster(500);
+
+        let tight = sim.force_accuracy(500, 1.0, 1.0, 0.3);
+        let loose = sim.force_accuracy(500, 1.0, 1.0, 1.0);
+
+        assert!(tight.mean_error < loose.mean_error);
+    }
+}
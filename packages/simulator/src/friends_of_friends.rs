@@ -0,0 +1,177 @@
+use serde::Serialize;
+
+use crate::simulation::Simulation;
+use crate::vector2::Vector2;
+
+/// Group ID of particles whose group is smaller than the minimum size.
+pub const UNGROUPED: i32 = -1;
+
+#[derive(Debug, Copy, Clone, Serialize)]
+pub struct Group {
+    pub id: i32,
+    pub members: usize,
+    pub mass: f32,
+    pub center: Vector2,
+    pub velocity: Vector2,
+}
+
+/// Friends-of-friends groups, heaviest first. `group_ids` holds each
+/// particle's index into `groups`, or `UNGROUPED`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Groups {
+    pub group_ids: Vec<i32>,
+    pub groups: Vec<Group>,
+}
+
+/// Disjoint sets over particle indices, with path halving and union by size.
+struct DisjointSets {
+    parents: Vec<usize>,
+    sizes: Vec<usize>,
+}
+
+impl DisjointSets {
+    fn new(count: usize) -> DisjointSets {
+        DisjointSets {
+            parents: (0..count).collect(),
+            sizes: vec![1; count],
+        }
+    }
+
+    fn find(&mut self, mut i: usize) -> usize {
+        while self.parents[i] != i {
+            self.parents[i] = self.parents[self.parents[i]];
+            i = self.parents[i];
+        }
+        i
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (mut a, mut b) = (self.find(a), self.find(b));
+        if a == b {
+            return;
+        }
+        if self.sizes[a] < self.sizes[b] {
+            std::mem::swap(&mut a, &mut b);
+        }
+        self.parents[b] = a;
+        self.sizes[a] += self.sizes[b];
+    }
+}
+
+impl Simulation {
+    /// Links every pair of particles closer than `linking_length` and
+    /// reports the connected groups with at least `min_members` particles.
+    pub fn friends_of_friends(&self, linking_length: f32, min_members: usize) -> Groups {
+        let tree = self.tree();
+        let mut sets = DisjointSets::new(self.count);
+
+        for i in 0..self.count {
+            let p_pos = Vector2::new(self.positions_x[i], self.positions_y[i]);
+            for j in tree.within_radius(p_pos, linking_length, &self.positions_x, &self.positions_y)
+            {
+                if j > i {
+                    sets.union(i, j);
+                }
+            }
+        }
+
+        // Mass, mass-weighted position and momentum of every root
+        let mut totals = vec![(0usize, 0.0, 0.0, 0.0, 0.0, 0.0); self.count];
+        let roots: Vec<usize> = (0..self.count).map(|i| sets.find(i)).collect();
+        for (i, &root) in roots.iter().enumerate() {
+            let m = self.masses[i] as f64;
+            let total = &mut totals[root];
+            total.0 += 1;
+            total.1 += m;
+            total.2 += m * self.positions_x[i] as f64;
+            total.3 += m * self.positions_y[i] as f64;
+            total.4 += m * self.velocities_x[i] as f64;
+            total.5 += m * self.velocities_y[i] as f64;
+        }
+
+        let mut kept: Vec<usize> = (0..self.count)
+            .filter(|&root| totals[root].0 > 0 && totals[root].0 >= min_members.max(1))
+            .collect();
+        kept.sort_by(|&a, &b| totals[b].1.total_cmp(&totals[a].1).then(a.cmp(&b)));
+
+        let mut root_ids = vec![UNGROUPED; self.count];
+        let groups: Vec<Group> = kept
+            .iter()
+            .enumerate()
+            .map(|(id, &root)| {
+                root_ids[root] = id as i32;
+                let (members, mass, mx, my, px, py) = totals[root];
+                // Massless groups fall back to an unweighted mean
+                let weight = if mass > 0.0 { mass } else { 1.0 };
+                Group {
+                    id: id as i32,
+                    members,
+                    mass: mass as f32,
+                    center: Vector2::new((mx / weight) as f32, (my / weight) as f32),
+                    velocity: Vector2::new((px / weight) as f32, (py / weight) as f32),
+                }
+            })
+            .collect();
+
+        Groups {
+            group_ids: roots.iter().map(|&root| root_ids[root]).collect(),
+            groups,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::particle::Particle;
+
+    fn body(x: f32, y: f32, vx: f32, mass: f32) -> Particle {
+        Particle::new(
+            mass,
+            1.0,
+            Vector2::new(x, y),
+            Vector2::new(vx, 0.0),
+            [255.0, 255.0, 255.0],
+        )
+    }
+
+    #[test]
+    fn test_chains_link_into_one_group() {
+        let sim = Simulation::new(vec![
+            // A chain whose ends are further apart than the linking length
+            body(10.0, 10.0, 1.0, 1.0),
+            body(12.0, 10.0, 1.0, 1.0),
+            body(14.0, 10.0, 1.0, 1.0),
+            // A heavier pair
+            body(80.0, 80.0, -1.0, 3.0),
+            body(81.0, 80.0, 1.0, 3.0),
+            // An isolated particle
+            body(50.0, 10.0, 0.0, 1.0),
+        ]);
+
+        let result = sim.friends_of_friends(2.5, 2);
+
+        assert_eq!(result.group_ids, vec![1, 1, 1, 0, 0, UNGROUPED]);
+        assert_eq!(result.groups.len(), 2);
+
+        let pair = result.groups[0];
+        assert_eq!(pair.members, 2);
+        assert_eq!(pair.mass, 6.0);
+        assert_eq!(pair.center.x, 80.5);
+        assert_eq!(pair.velocity.x, 0.0);
+
+        let chain = result.groups[1];
+        assert_eq!(chain.members, 3);
+        assert_eq!(chain.center.x, 12.0);
+        assert_eq!(chain.velocity.x, 1.0);
+    }
+
+    #[test]
+    fn test_single_member_groups() {
+        let sim = Simulation::new(vec![body(10.0, 10.0, 0.0, 1.0), body(90.0, 90.0, 0.0, 2.0)]);
+
+        let result = sim.friends_of_friends(1.0, 1);
+
+        assert_eq!(result.group_ids, vec![1, 0]);
+    }
+}
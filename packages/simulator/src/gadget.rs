@@ -0,0 +1,644 @@
+use std::collections::HashSet;
+use std::fmt;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::particle::Particle;
+use crate::projection::ProjectionAxis;
+use crate::simulation::Simulation;
+use crate::utils::binary_utils::{ByteReader, ByteWriter};
+
+const HEADER_SIZE: usize = 256;
+
+/// Bytes the fields below take up; the rest of the header is padding.
+const HEADER_FIELDS_SIZE: usize = 196;
+
+/// GADGET-2 (`SnapFormat=2`) prefixes every block with a record holding a
+/// four-character label and the size of the block that follows.
+const LABEL_RECORD_SIZE: u32 = 8;
+
+/// Particle type written on export, "halo" in GADGET's convention.
+const EXPORT_TYPE: usize = 1;
+
+/// One color per GADGET particle type: gas, halo, disk, bulge, stars, boundary.
+const TYPE_COLORS: [[f32; 3]; 6] = [
+    [80.0, 160.0, 255.0],
+    [200.0, 200.0, 200.0],
+    [255.0, 220.0, 120.0],
+    [255.0, 150.0, 80.0],
+    [255.0, 255.0, 255.0],
+    [255.0, 80.0, 80.0],
+];
+
+/// Snapshot layout: plain Fortran records (`SnapFormat=1`), or records each
+/// preceded by a label record (`SnapFormat=2`).
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum GadgetFormat {
+    #[default]
+    One,
+    Two,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct GadgetHeader {
+    pub npart: [u32; 6],
+    /// Per-type mass; zero means the type's masses are in the mass block
+    pub mass: [f64; 6],
+    pub time: f64,
+    pub redshift: f64,
+    pub flag_sfr: i32,
+    pub flag_feedback: i32,
+    pub npart_total: [u32; 6],
+    pub flag_cooling: i32,
+    pub num_files: i32,
+    pub box_size: f64,
+    pub omega0: f64,
+    pub omega_lambda: f64,
+    pub hubble_param: f64,
+    pub flag_stellar_age: i32,
+    pub flag_metals: i32,
+    pub npart_total_high_word: [u32; 6],
+    pub flag_entropy_instead_u: i32,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GadgetError {
+    Truncated,
+    /// The Fortran record markers around a block disagree
+    RecordMismatch,
+    /// A block's size doesn't fit the particle counts in the header
+    UnexpectedBlockSize(&'static str),
+    /// The header counts more particles than can be addressed
+    TooManyParticles,
+    /// A snapshot's particle array doesn't fit the counts in its header
+    LengthMismatch(&'static str),
+    /// An ID that doesn't fit in 32 bits
+    IdOutOfRange,
+    DuplicateId,
+    /// Snapshots split over several files aren't supported
+    MultipleFiles,
+}
+
+impl fmt::Display for GadgetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GadgetError::Truncated => write!(f, "GADGET snapshot is truncated"),
+            GadgetError::RecordMismatch => write!(f, "GADGET record markers don't match"),
+            GadgetError::UnexpectedBlockSize(block) => {
+                write!(f, "Unexpected size of GADGET {} block", block)
+            }
+            GadgetError::TooManyParticles => write!(f, "Too many particles in GADGET header"),
+            GadgetError::LengthMismatch(array) => write!(
+                f,
+                "GADGET {} don't match the particle counts in the header",
+                array
+            ),
+            GadgetError::IdOutOfRange => write!(f, "GADGET particle IDs must fit in 32 bits"),
+            GadgetError::DuplicateId => write!(f, "GADGET particle IDs must be unique"),
+            GadgetError::MultipleFiles => {
+                write!(f, "Multi-file GADGET snapshots aren't supported")
+            }
+        }
+    }
+}
+
+impl GadgetHeader {
+    fn read(bytes: &[u8]) -> Option<GadgetHeader> {
+        let mut reader = ByteReader::new(bytes);
+        let mut header = GadgetHeader::default();
+
+        for n in header.npart.iter_mut() {
+            *n = reader.read_u32()?;
+        }
+        for m in header.mass.iter_mut() {
+            *m = reader.read_f64()?;
+        }
+        header.time = reader.read_f64()?;
+        header.redshift = reader.read_f64()?;
+        header.flag_sfr = reader.read_i32()?;
+        header.flag_feedback = reader.read_i32()?;
+        for n in header.npart_total.iter_mut() {
+            *n = reader.read_u32()?;
+        }
+        header.flag_cooling = reader.read_i32()?;
+        header.num_files = reader.read_i32()?;
+        header.box_size = reader.read_f64()?;
+        header.omega0 = reader.read_f64()?;
+        header.omega_lambda = reader.read_f64()?;
+        header.hubble_param = reader.read_f64()?;
+        header.flag_stellar_age = reader.read_i32()?;
+        header.flag_metals = reader.read_i32()?;
+        for n in header.npart_total_high_word.iter_mut() {
+            *n = reader.read_u32()?;
+        }
+        header.flag_entropy_instead_u = reader.read_i32()?;
+
+        Some(header)
+    }
+
+    fn write(&self, writer: &mut ByteWriter) {
+        for &n in &self.npart {
+            writer.write_u32(n);
+        }
+        for &m in &self.mass {
+            writer.write_f64(m);
+        }
+        writer.write_f64(self.time);
+        writer.write_f64(self.redshift);
+        writer.write_i32(self.flag_sfr);
+        writer.write_i32(self.flag_feedback);
+        for &n in &self.npart_total {
+            writer.write_u32(n);
+        }
+        writer.write_i32(self.flag_cooling);
+        writer.write_i32(self.num_files);
+        writer.write_f64(self.box_size);
+        writer.write_f64(self.omega0);
+        writer.write_f64(self.omega_lambda);
+        writer.write_f64(self.hubble_param);
+        writer.write_i32(self.flag_stellar_age);
+        writer.write_i32(self.flag_metals);
+        for &n in &self.npart_total_high_word {
+            writer.write_u32(n);
+        }
+        writer.write_i32(self.flag_entropy_instead_u);
+        writer.write_zeros(HEADER_SIZE - HEADER_FIELDS_SIZE);
+    }
+
+    /// Total particles, `None` when they overflow `usize` as they can on
+    /// wasm32.
+    fn particle_count(&self) -> Option<usize> {
+        self.npart
+            .iter()
+            .try_fold(0usize, |total, &n| total.checked_add(n as usize))
+    }
+
+    /// Type of every particle in file order.
+    fn particle_types(&self) -> impl Iterator<Item = usize> + '_ {
+        self.npart
+            .iter()
+            .enumerate()
+            .flat_map(|(particle_type, &n)| (0..n).map(move |_| particle_type))
+    }
+
+    /// Particles whose mass is stored individually in the mass block. Never
+    /// more than `particle_count`, so it can't overflow once that didn't.
+    fn variable_mass_count(&self) -> usize {
+        self.npart
+            .iter()
+            .zip(self.mass.iter())
+            .filter(|(_, &m)| m == 0.0)
+            .map(|(&n, _)| n as usize)
+            .sum()
+    }
+}
+
+/// 3D particle data as stored in a snapshot, types in ascending order.
+/// `masses` holds every particle's mass, whether it came from the header's
+/// mass table or from the mass block.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct GadgetSnapshot {
+    pub header: GadgetHeader,
+    pub positions: Vec<[f32; 3]>,
+    pub velocities: Vec<[f32; 3]>,
+    pub ids: Vec<u64>,
+    pub masses: Vec<f32>,
+}
+
+fn read_record<'a>(reader: &mut ByteReader<'a>) -> Result<&'a [u8], GadgetError> {
+    let len = reader.read_u32().ok_or(GadgetError::Truncated)?;
+    let data = reader
+        .read_bytes(len as usize)
+        .ok_or(GadgetError::Truncated)?;
+    if reader.read_u32() != Some(len) {
+        return Err(GadgetError::RecordMismatch);
+    }
+    Ok(data)
+}
+
+/// The next data block, skipping its label record in format 2.
+fn next_block<'a>(reader: &mut ByteReader<'a>, labelled: bool) -> Result<&'a [u8], GadgetError> {
+    if labelled {
+        read_record(reader)?;
+    }
+    read_record(reader)
+}
+
+fn write_record(writer: &mut ByteWriter, len: usize, body: impl FnOnce(&mut ByteWriter)) {
+    writer.write_u32(len as u32);
+    body(writer);
+    writer.write_u32(len as u32);
+}
+
+/// Writes a data record, preceded in format 2 by its label record.
+fn write_block(
+    writer: &mut ByteWriter,
+    format: GadgetFormat,
+    label: &[u8; 4],
+    len: usize,
+    body: impl FnOnce(&mut ByteWriter),
+) {
+    if format == GadgetFormat::Two {
+        write_record(writer, LABEL_RECORD_SIZE as usize, |w| {
+            w.write_bytes(label);
+            // Size of the data record, markers included
+            w.write_u32(len as u32 + 8);
+        });
+    }
+    write_record(writer, len, body);
+}
+
+/// Checks a block holds `count` values of `size` bytes each.
+fn check_block_size(
+    block: &[u8],
+    count: usize,
+    size: usize,
+    name: &'static str,
+) -> Result<(), GadgetError> {
+    match count.checked_mul(size) {
+        Some(len) if len == block.len() => Ok(()),
+        _ => Err(GadgetError::UnexpectedBlockSize(name)),
+    }
+}
+
+fn read_vectors(block: &[u8]) -> Vec<[f32; 3]> {
+    block
+        .chunks_exact(12)
+        .map(|chunk| {
+            let mut reader = ByteReader::new(chunk);
+            [0; 3].map(|_| reader.read_f32().unwrap())
+        })
+        .collect()
+}
+
+impl GadgetSnapshot {
+    /// Reads a single-file, little-endian snapshot in format 1 or 2. Blocks
+    /// after the masses (gas properties and so on) are ignored.
+    pub fn from_bytes(bytes: &[u8]) -> Result<GadgetSnapshot, GadgetError> {
+        let mut reader = ByteReader::new(bytes);
+        let labelled = bytes.get(..4) == Some(&LABEL_RECORD_SIZE.to_le_bytes()[..]);
+
+        let header_block = next_block(&mut reader, labelled)?;
+        if header_block.len() != HEADER_SIZE {
+            return Err(GadgetError::UnexpectedBlockSize("header"));
+        }
+        let header = GadgetHeader::read(header_block).ok_or(GadgetError::Truncated)?;
+        if header.num_files > 1 {
+            return Err(GadgetError::MultipleFiles);
+        }
+        let count = header
+            .particle_count()
+            .ok_or(GadgetError::TooManyParticles)?;
+
+        let positions = next_block(&mut reader, labelled)?;
+        check_block_size(positions, count, 12, "position")?;
+        let velocities = next_block(&mut reader, labelled)?;
+        check_block_size(velocities, count, 12, "velocity")?;
+
+        let id_block = next_block(&mut reader, labelled)?;
+        let mut id_reader = ByteReader::new(id_block);
+        let ids: Vec<u64> = if check_block_size(id_block, count, 4, "ID").is_ok() {
+            (0..count)
+                .map(|_| id_reader.read_u32().unwrap() as u64)
+                .collect()
+        } else {
+            check_block_size(id_block, count, 8, "ID")?;
+            (0..count).map(|_| id_reader.read_u64().unwrap()).collect()
+        };
+
+        let variable_count = header.variable_mass_count();
+        let stored_masses = if variable_count > 0 {
+            let block = next_block(&mut reader, labelled)?;
+            check_block_size(block, variable_count, 4, "mass")?;
+            ByteReader::new(block).read_f32_vec(variable_count).unwrap()
+        } else {
+            Vec::new()
+        };
+
+        let mut stored = stored_masses.into_iter();
+        let masses = header
+            .particle_types()
+            .map(|particle_type| match header.mass[particle_type] {
+                0.0 => stored.next().unwrap_or(0.0),
+                m => m as f32,
+            })
+            .collect();
+
+        Ok(GadgetSnapshot {
+            header,
+            positions: read_vectors(positions),
+            velocities: read_vectors(velocities),
+            ids,
+            masses,
+        })
+    }
+
+    /// Writes 32-bit IDs. Masses go in the mass block only for types
+    /// without an entry in the header's mass table. The snapshot is
+    /// validated first.
+    pub fn to_bytes(&self, format: GadgetFormat) -> Result<Vec<u8>, GadgetError> {
+        self.validate()?;
+        let count = self.positions.len();
+        let mut writer = ByteWriter::new();
+
+        write_block(&mut writer, format, b"HEAD", HEADER_SIZE, |w| {
+            self.header.write(w)
+        });
+        for (label, vectors) in [(b"POS ", &self.positions), (b"VEL ", &self.velocities)] {
+            write_block(&mut writer, format, label, count * 12, |w| {
+                vectors.iter().for_each(|v| w.write_f32_slice(v))
+            });
+        }
+        write_block(&mut writer, format, b"ID  ", count * 4, |w| {
+            self.ids.iter().for_each(|&id| w.write_u32(id as u32))
+        });
+
+        let variable_masses: Vec<f32> = self
+            .header
+            .particle_types()
+            .zip(self.masses.iter())
+            .filter(|(particle_type, _)| self.header.mass[*particle_type] == 0.0)
+            .map(|(_, &m)| m)
+            .collect();
+        if !variable_masses.is_empty() {
+            write_block(
+                &mut writer,
+                format,
+                b"MASS",
+                variable_masses.len() * 4,
+                |w| w.write_f32_slice(&variable_masses),
+            );
+        }
+
+        Ok(writer.into_bytes())
+    }
+
+    /// Checks every particle array holds one entry per particle in the
+    /// header, as `from_bytes` guarantees but hand-built snapshots may not,
+    /// and that the snapshot fits in one file with 32-bit IDs.
+    pub fn validate(&self) -> Result<(), GadgetError> {
+        if self.header.num_files > 1 {
+            return Err(GadgetError::MultipleFiles);
+        }
+        // The largest block, positions or velocities, must fit a u32 record
+        let count = self
+            .header
+            .particle_count()
+            .filter(|&count| {
+                count
+                    .checked_mul(12)
+                    .is_some_and(|len| u32::try_from(len).is_ok())
+            })
+            .ok_or(GadgetError::TooManyParticles)?;
+        let lengths = [
+            (self.positions.len(), "positions"),
+            (self.velocities.len(), "velocities"),
+            (self.ids.len(), "IDs"),
+            (self.masses.len(), "masses"),
+        ];
+        if let Some(&(_, array)) = lengths.iter().find(|(len, _)| *len != count) {
+            return Err(GadgetError::LengthMismatch(array));
+        }
+
+        let mut seen = HashSet::with_capacity(count);
+        for &id in &self.ids {
+            if id > u32::MAX as u64 {
+                return Err(GadgetError::IdOutOfRange);
+            }
+            if !seen.insert(id) {
+                return Err(GadgetError::DuplicateId);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Simulation {
+    /// Flattens a snapshot along `axis`, coloring particles by type. The
+    /// snapshot is validated first, so IDs are kept rather than renumbered.
+    pub fn from_gadget(
+        snapshot: &GadgetSnapshot,
+        axis: ProjectionAxis,
+        diameter: f32,
+    ) -> Result<Simulation, GadgetError> {
+        snapshot.validate()?;
+
+        let particles = snapshot
+            .header
+            .particle_types()
+            .enumerate()
+            .map(|(i, particle_type)| {
+                let mut particle = Particle::new(
+                    snapshot.masses[i],
+                    diameter,
+                    axis.project(snapshot.positions[i]),
+                    axis.project(snapshot.velocities[i]),
+                    TYPE_COLORS[particle_type],
+                );
+                // GADGET IDs are unsigned; keep their 32-bit pattern, as
+                // `to_gadget` writes it
+                particle.id = snapshot.ids[i] as u32 as i32;
+                particle
+            })
+            .collect();
+
+        let mut sim = Simulation::new(particles);
+        sim.time = snapshot.header.time;
+        Ok(sim)
+    }
+
+    /// All particles as one type with z = 0, in a box as large as the world.
+    pub fn to_gadget(&self) -> GadgetSnapshot {
+        let mut header = GadgetHeader {
+            time: self.time,
+            num_files: 1,
+            box_size: self.params.world_size.x.max(self.params.world_size.y) as f64,
+            ..GadgetHeader::default()
+        };
+        header.npart[EXPORT_TYPE] = self.count as u32;
+        header.npart_total[EXPORT_TYPE] = self.count as u32;
+
+        GadgetSnapshot {
+            header,
+            positions: (0..self.count)
+                .map(|i| [self.positions_x[i], self.positions_y[i], 0.0])
+                .collect(),
+            velocities: (0..self.count)
+                .map(|i| [self.velocities_x[i], self.velocities_y[i], 0.0])
+                .collect(),
+            ids: self.ids.iter().map(|&id| id as u32 as u64).collect(),
+            masses: self.masses.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot() -> GadgetSnapshot {
+        let mut header = GadgetHeader {
+            time: 0.5,
+            num_files: 1,
+            box_size: 100.0,
+            ..GadgetHeader::default()
+        };
+        // Two gas particles with individual masses, one star sharing a fixed mass
+        header.npart[0] = 2;
+        header.npart[4] = 1;
+        header.mass[4] = 7.0;
+        header.npart_total = header.npart;
+
+        GadgetSnapshot {
+            header,
+            positions: vec![[1.0, 2.0, 3.0], [4.0, 5.0, 6.0], [7.0, 8.0, 9.0]],
+            velocities: vec![[0.1, 0.2, 0.3], [0.4, 0.5, 0.6], [0.7, 0.8, 0.9]],
+            ids: vec![10, 11, 4_000_000_000],
+            masses: vec![1.0, 2.0, 7.0],
+        }
+    }
+
+    #[test]
+    fn test_round_trip_with_mass_table() {
+        let bytes = snapshot().to_bytes(GadgetFormat::One).unwrap();
+
+        // Header, positions, velocities, IDs and the two gas masses
+        let expected_len = (256 + 8) + 2 * (36 + 8) + (12 + 8) + (8 + 8);
+        assert_eq!(bytes.len(), expected_len);
+        assert_eq!(GadgetSnapshot::from_bytes(&bytes).unwrap(), snapshot());
+    }
+
+    #[test]
+    fn test_projection_on_import() {
+        let sim = Simulation::from_gadget(&snapshot(), ProjectionAxis::X, 1.0).unwrap();
+
+        assert_eq!(sim.positions_x, vec![2.0, 5.0, 8.0]);
+        assert_eq!(sim.positions_y, vec![3.0, 6.0, 9.0]);
+        assert_eq!(sim.velocities_y, vec![0.3, 0.6, 0.9]);
+        assert_eq!(sim.masses, vec![1.0, 2.0, 7.0]);
+        assert_eq!(sim.time, 0.5);
+        assert_eq!(&sim.colors[6..], &TYPE_COLORS[4]);
+    }
+
+    #[test]
+    fn test_simulation_round_trip() {
+        let sim = Simulation::from_gadget(&snapshot(), ProjectionAxis::Z, 1.0).unwrap();
+
+        let bytes = sim.to_gadget().to_bytes(GadgetFormat::One).unwrap();
+        let restored = Simulation::from_gadget(
+            &GadgetSnapshot::from_bytes(&bytes).unwrap(),
+            ProjectionAxis::Z,
+            1.0,
+        )
+        .unwrap();
+
+        assert_eq!(restored.ids, sim.ids);
+        assert_eq!(restored.positions_x, sim.positions_x);
+        assert_eq!(restored.velocities_y, sim.velocities_y);
+        assert_eq!(restored.masses, sim.masses);
+    }
+
+    #[test]
+    fn test_format_two_labels() {
+        let plain = snapshot().to_bytes(GadgetFormat::One).unwrap();
+        let mut reader = ByteReader::new(&plain);
+        let mut writer = ByteWriter::new();
+        for label in [b"HEAD", b"POS ", b"VEL ", b"ID  ", b"MASS"] {
+            let block = read_record(&mut reader).unwrap();
+            write_record(&mut writer, 8, |w| {
+                w.write_bytes(label);
+                w.write_u32(block.len() as u32 + 8);
+            });
+            write_record(&mut writer, block.len(), |w| w.write_bytes(block));
+        }
+        let expected = writer.into_bytes();
+
+        let labelled = snapshot().to_bytes(GadgetFormat::Two).unwrap();
+
+        assert_eq!(labelled, expected);
+        assert_eq!(GadgetSnapshot::from_bytes(&labelled).unwrap(), snapshot());
+    }
+
+    #[test]
+    fn test_oversized_counts() {
+        assert_eq!(
+            check_block_size(&[0; 12], usize::MAX, 12, "position"),
+            Err(GadgetError::UnexpectedBlockSize("position"))
+        );
+
+        // Patch the counts at the start of the header record
+        let mut bytes = snapshot().to_bytes(GadgetFormat::One).unwrap();
+        bytes[4..28].fill(0xff);
+        assert_eq!(
+            GadgetSnapshot::from_bytes(&bytes).unwrap_err(),
+            GadgetError::UnexpectedBlockSize("position")
+        );
+    }
+
+    #[test]
+    fn test_mismatched_arrays_are_rejected() {
+        let mut short = snapshot();
+        short.ids.pop();
+
+        assert_eq!(
+            Simulation::from_gadget(&short, ProjectionAxis::Z, 1.0).unwrap_err(),
+            GadgetError::LengthMismatch("IDs")
+        );
+    }
+
+    #[test]
+    fn test_ids_must_fit_32_bits_and_be_unique() {
+        let mut wide = snapshot();
+        wide.ids[0] = u32::MAX as u64 + 1;
+        assert_eq!(wide.validate(), Err(GadgetError::IdOutOfRange));
+        assert_eq!(
+            wide.to_bytes(GadgetFormat::One),
+            Err(GadgetError::IdOutOfRange)
+        );
+
+        let mut shared = snapshot();
+        shared.ids[1] = shared.ids[0];
+        assert_eq!(
+            Simulation::from_gadget(&shared, ProjectionAxis::Z, 1.0).unwrap_err(),
+            GadgetError::DuplicateId
+        );
+    }
+
+    #[test]
+    fn test_multiple_files_are_rejected() {
+        let mut split = snapshot();
+        split.header.num_files = 2;
+        assert_eq!(
+            split.to_bytes(GadgetFormat::One),
+            Err(GadgetError::MultipleFiles)
+        );
+
+        let mut bytes = snapshot().to_bytes(GadgetFormat::One).unwrap();
+        // num_files follows npart, mass, time, redshift, two flags,
+        // npart_total and flag_cooling
+        let offset = 4 + 24 + 48 + 16 + 8 + 24 + 4;
+        bytes[offset..offset + 4].copy_from_slice(&2i32.to_le_bytes());
+        assert_eq!(
+            GadgetSnapshot::from_bytes(&bytes).unwrap_err(),
+            GadgetError::MultipleFiles
+        );
+    }
+
+    #[test]
+    fn test_corrupt_records() {
+        let mut bytes = snapshot().to_bytes(GadgetFormat::One).unwrap();
+        bytes[4 + HEADER_SIZE] ^= 1;
+        assert_eq!(
+            GadgetSnapshot::from_bytes(&bytes).unwrap_err(),
+            GadgetError::RecordMismatch
+        );
+
+        let bytes = snapshot().to_bytes(GadgetFormat::One).unwrap();
+        assert_eq!(
+            GadgetSnapshot::from_bytes(&bytes[..100]).unwrap_err(),
+            GadgetError::Truncated
+        );
+    }
+}
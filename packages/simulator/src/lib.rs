@@ -1,86 +1,77 @@
-mod particle;
-mod quad_tree;
+//! Barnes-Hut N-body engine behind the web simulator, usable from Rust as
+//! well. The JS bindings live behind the default `wasm` feature; native
+//! tools can depend on the crate with `default-features = false`.
+//!
+//! ```
+//! use nbody_simulator::{Particle, PotentialMethod, SimulationBuilder, Vector2};
+//!
+//! let mut sim = SimulationBuilder::new()
+//!     .with_world_size(100.0, 100.0)
+//!     .with_gravity(1.0)
+//!     .with_epsilon(0.5)
+//!     .with_time_step(0.01)
+//!     .with_particles([
+//!         Particle::new(1.0, 1.0, Vector2::new(40.0, 50.0), Vector2::new(0.0, -0.1), [255.0; 3]),
+//!         Particle::new(1.0, 1.0, Vector2::new(60.0, 50.0), Vector2::new(0.0, 0.1), [255.0; 3]),
+//!     ])
+//!     .build()
+//!     .unwrap();
+//!
+//! sim.run(100);
+//! let diagnostics = sim.diagnostics(PotentialMethod::Direct);
+//! assert!(diagnostics.total_energy.is_finite());
+//! ```
+
+pub mod builder;
+pub mod checkpoint;
+pub mod csv;
+pub mod density;
+pub mod diagnostics;
+pub mod drift_tracker;
+pub mod few_body;
+pub mod force_accuracy;
+pub mod friends_of_friends;
+pub mod gadget;
+pub mod manipulation;
+pub mod mass_distribution;
+pub mod npy;
+pub mod orbit_analysis;
+pub mod orbital_elements;
+pub mod particle;
+pub mod particle_generator;
+pub mod picking;
+pub mod projection;
+pub mod quad_tree;
 mod quadrant;
-mod rectangle;
+pub mod radial_profile;
+pub mod rectangle;
+pub mod scene;
+pub mod simulation;
+pub mod snapshot;
+pub mod spatial_distribution;
+pub mod tipsy;
+pub mod trajectory;
 mod utils;
-mod vector2;
-mod simulation;
-
-extern crate wasm_bindgen;
-
-use particle::Particle;
-use vector2::Vector2;
-use wasm_bindgen::prelude::*;
-use simulation::Simulation;
-
-#[wasm_bindgen]
-pub struct SimulationWrapper {
-    inner: Simulation,
-}
-
-#[wasm_bindgen]
-impl SimulationWrapper {
-    #[wasm_bindgen(constructor)]
-    pub fn new(particles: Vec<Particle>) -> SimulationWrapper {
-        SimulationWrapper {
-            inner: Simulation::new(particles),
-        }
-    }
-
-    pub fn step(&mut self, world_width: f32, world_height: f32, gravity: f32, epsilon: f32, time_step: f32) {
-        let world_size = Vector2::new(world_width, world_height);
-        self.inner.step(world_size, gravity, epsilon, time_step);
-    }
-
-    pub fn positions_x_ptr(&self) -> *const f32 {
-        self.inner.positions_x.as_ptr()
-    }
-
-    pub fn positions_y_ptr(&self) -> *const f32 {
-        self.inner.positions_y.as_ptr()
-    }
-
-    pub fn velocities_x_ptr(&self) -> *const f32 {
-        self.inner.velocities_x.as_ptr()
-    }
-
-    pub fn velocities_y_ptr(&self) -> *const f32 {
-        self.inner.velocities_y.as_ptr()
-    }
-
-    pub fn masses_ptr(&self) -> *const f32 {
-        self.inner.masses.as_ptr()
-    }
-
-    pub fn diameters_ptr(&self) -> *const f32 {
-        self.inner.diameters.as_ptr()
-    }
-
-    pub fn colors_ptr(&self) -> *const f32 {
-        self.inner.colors.as_ptr()
-    }
-
-    pub fn count(&self) -> usize {
-        self.inner.count
-    }
-}
+pub mod vector2;
+pub mod velocity_profile;
+pub mod vtk;
 
-#[wasm_bindgen]
-pub fn generate_particles(
-    number: usize,
-    world_width: f32,
-    world_height: f32,
-    mass: f32,
-    mass_deviation: f32,
-    diameter: f32,
-) -> SimulationWrapper {
-    let world_size = Vector2::new(world_width, world_height);
+#[cfg(feature = "wasm")]
+mod wasm;
 
-    let particles: Vec<Particle> = (0..number)
-        .map(|_| Particle::new_rand(world_size, mass, mass_deviation, diameter))
-        .collect();
-    
-    SimulationWrapper {
-        inner: Simulation::new(particles),
-    }
-}
+pub use builder::{BuildError, SimulationBuilder};
+pub use density::DensityEstimator;
+pub use diagnostics::{Diagnostics, PotentialMethod};
+pub use drift_tracker::DriftTracker;
+pub use manipulation::EditError;
+pub use mass_distribution::{DiameterLaw, MassDistribution, MassDistributionError};
+pub use particle::Particle;
+pub use particle_generator::GenerationOptions;
+pub use quad_tree::QuadTree;
+pub use rectangle::Rectangle;
+pub use scene::{Scene, SceneError, SceneMetadata};
+pub use simulation::{Simulation, SimulationParams};
+pub use spatial_distribution::{SpatialDistribution, SpatialDistributionError};
+pub use trajectory::{TrajectoryError, TrajectoryPlayer, TrajectoryRecorder};
+pub use vector2::Vector2;
+pub use velocity_profile::VelocityProfile;
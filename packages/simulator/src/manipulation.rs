@@ -0,0 +1,222 @@
+use std::fmt;
+
+use crate::simulation::Simulation;
+use crate::vector2::Vector2;
+
+/// Why an edit was refused. The particle is left unchanged.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EditError {
+    IndexOutOfRange,
+    NonFinite,
+    InvalidMass,
+    InvalidColor,
+    /// Impulses on a massless particle would give it an infinite velocity
+    Massless,
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EditError::IndexOutOfRange => write!(f, "No particle at that index"),
+            EditError::NonFinite => write!(f, "Positions, velocities and impulses must be finite"),
+            EditError::InvalidMass => write!(f, "Mass must be finite and positive"),
+            EditError::InvalidColor => write!(f, "Color channels must be within 0-255"),
+            EditError::Massless => write!(f, "Massless particles can't take an impulse"),
+        }
+    }
+}
+
+fn check_finite(v: Vector2) -> Result<(), EditError> {
+    if v.x.is_finite() && v.y.is_finite() {
+        Ok(())
+    } else {
+        Err(EditError::NonFinite)
+    }
+}
+
+/// Direct edits to one particle, for grabbing and flinging bodies from the
+/// UI. Every edit restarts drift tracking from the edited state, since
+/// conservation no longer holds across it.
+impl Simulation {
+    pub fn set_position(&mut self, index: usize, position: Vector2) -> Result<(), EditError> {
+        self.check_index(index)?;
+        check_finite(position)?;
+
+        self.positions_x[index] = position.x;
+        self.positions_y[index] = position.y;
+        self.invalidate_tree();
+        self.rebase_drift();
+        Ok(())
+    }
+
+    pub fn set_velocity(&mut self, index: usize, velocity: Vector2) -> Result<(), EditError> {
+        self.check_index(index)?;
+        check_finite(velocity)?;
+
+        self.velocities_x[index] = velocity.x;
+        self.velocities_y[index] = velocity.y;
+        self.rebase_drift();
+        Ok(())
+    }
+
+    pub fn set_mass(&mut self, index: usize, mass: f32) -> Result<(), EditError> {
+        self.check_index(index)?;
+        if !(mass > 0.0 && mass.is_finite()) {
+            return Err(EditError::InvalidMass);
+        }
+
+        self.masses[index] = mass;
+        self.invalidate_tree();
+        self.rebase_drift();
+        Ok(())
+    }
+
+    pub fn set_color(&mut self, index: usize, color: [f32; 3]) -> Result<(), EditError> {
+        self.check_index(index)?;
+        if !color.iter().all(|c| (0.0..=255.0).contains(c)) {
+            return Err(EditError::InvalidColor);
+        }
+
+        self.colors[index * 3..index * 3 + 3].copy_from_slice(&color);
+        Ok(())
+    }
+
+    /// Changes the velocity by `impulse / mass`.
+    pub fn apply_impulse(&mut self, index: usize, impulse: Vector2) -> Result<(), EditError> {
+        self.check_index(index)?;
+        check_finite(impulse)?;
+        let mass = self.masses[index];
+        if mass == 0.0 {
+            return Err(EditError::Massless);
+        }
+
+        self.velocities_x[index] += impulse.x / mass;
+        self.velocities_y[index] += impulse.y / mass;
+        self.rebase_drift();
+        Ok(())
+    }
+
+    fn check_index(&self, index: usize) -> Result<(), EditError> {
+        if index < self.count {
+            Ok(())
+        } else {
+            Err(EditError::IndexOutOfRange)
+        }
+    }
+
+    fn rebase_drift(&mut self) {
+        if let Some(tracker) = self.drift.as_mut() {
+            tracker.clear_baseline();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::PotentialMethod;
+    use crate::particle::Particle;
+
+    fn two_bodies() -> Simulation {
+        Simulation::new(vec![
+            Particle::new(
+                2.0,
+                1.0,
+                Vector2::new(10.0, 10.0),
+                Vector2::new(0.0, 0.0),
+                [255.0, 255.0, 255.0],
+            ),
+            Particle::new(
+                1.0,
+                1.0,
+                Vector2::new(20.0, 10.0),
+                Vector2::new(0.0, 0.0),
+                [255.0, 255.0, 255.0],
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_edits_update_soa_arrays() {
+        let mut sim = two_bodies();
+
+        sim.set_position(1, Vector2::new(30.0, 40.0)).unwrap();
+        sim.set_velocity(1, Vector2::new(1.0, 2.0)).unwrap();
+        sim.set_mass(1, 4.0).unwrap();
+        sim.set_color(1, [1.0, 2.0, 3.0]).unwrap();
+        sim.apply_impulse(0, Vector2::new(4.0, -2.0)).unwrap();
+
+        let edited = sim.particle(1);
+        assert_eq!((edited.position.x, edited.position.y), (30.0, 40.0));
+        assert_eq!((edited.velocity.x, edited.velocity.y), (1.0, 2.0));
+        assert_eq!(edited.mass, 4.0);
+        assert_eq!(&sim.colors[3..], &[1.0, 2.0, 3.0]);
+        assert_eq!(&sim.colors[..3], &[255.0, 255.0, 255.0]);
+        assert_eq!((sim.velocities_x[0], sim.velocities_y[0]), (2.0, -1.0));
+    }
+
+    #[test]
+    fn test_edits_by_id_with_shared_ids() {
+        let bodies = two_bodies();
+        let shared = bodies.ids()[0];
+        let mut particles = vec![bodies.particle(0), bodies.particle(1)];
+        particles[1].id = shared;
+        let mut sim = Simulation::new(particles);
+
+        let second = sim.ids()[1];
+        assert_ne!(second, shared);
+        assert_eq!(sim.index_of(shared), Some(0));
+
+        let index = sim.index_of(second).unwrap();
+        sim.set_position(index, Vector2::new(30.0, 40.0)).unwrap();
+        sim.apply_impulse(index, Vector2::new(1.0, 0.0)).unwrap();
+
+        assert_eq!((sim.positions_x[0], sim.velocities_x[0]), (10.0, 0.0));
+        assert_eq!((sim.positions_x[1], sim.velocities_x[1]), (30.0, 1.0));
+    }
+
+    #[test]
+    fn test_edits_restart_drift_tracking() {
+        let mut sim = two_bodies();
+        sim.track_drift(1, 4, PotentialMethod::Direct);
+        sim.step(Vector2::new(100.0, 100.0), 1.0, 0.1, 0.01);
+        assert!(sim.drift.as_ref().unwrap().has_baseline());
+
+        sim.apply_impulse(1, Vector2::new(1.0, 0.0)).unwrap();
+
+        assert!(!sim.drift.as_ref().unwrap().has_baseline());
+    }
+
+    #[test]
+    fn test_invalid_edits_are_rejected_and_change_nothing() {
+        let mut sim = two_bodies();
+        let nan = Vector2::new(f32::NAN, 0.0);
+        let infinite = Vector2::new(0.0, f32::INFINITY);
+
+        assert_eq!(
+            sim.set_position(2, Vector2::new(0.0, 0.0)),
+            Err(EditError::IndexOutOfRange)
+        );
+        assert_eq!(sim.set_position(0, nan), Err(EditError::NonFinite));
+        assert_eq!(sim.set_velocity(0, infinite), Err(EditError::NonFinite));
+        assert_eq!(sim.apply_impulse(0, nan), Err(EditError::NonFinite));
+        for mass in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+            assert_eq!(sim.set_mass(0, mass), Err(EditError::InvalidMass));
+        }
+        for color in [[-1.0, 0.0, 0.0], [0.0, 256.0, 0.0], [0.0, 0.0, f32::NAN]] {
+            assert_eq!(sim.set_color(0, color), Err(EditError::InvalidColor));
+        }
+
+        let original = two_bodies();
+        assert_eq!(sim.positions_x, original.positions_x);
+        assert_eq!(sim.velocities_y, original.velocities_y);
+        assert_eq!(sim.masses, original.masses);
+        assert_eq!(sim.colors, original.colors);
+
+        sim.masses[1] = 0.0;
+        assert_eq!(
+            sim.apply_impulse(1, Vector2::new(1.0, 0.0)),
+            Err(EditError::Massless)
+        );
+    }
+}
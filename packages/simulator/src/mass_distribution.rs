@@ -0,0 +1,362 @@
+use std::fmt;
+
+use rand::prelude::*;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::utils::random_utils::standard_normal;
+
+/// Salpeter (1955) slope of the initial mass function, dN/dm ∝ m^-2.35.
+const SALPETER_ALPHA: f32 = 2.35;
+
+/// Kroupa (2001) slopes below and above the break mass.
+const KROUPA_ALPHA_LOW: f32 = 1.3;
+const KROUPA_ALPHA_HIGH: f32 = 2.3;
+
+/// Redraws of a normal mass before falling back to the mean. With a positive
+/// mean each draw is positive at least half the time.
+const MAX_NORMAL_REDRAWS: usize = 64;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum MassLaw {
+    Uniform { mean: f32, deviation: f32 },
+    Normal { mean: f32, std_dev: f32 },
+    LogNormal { median: f32, sigma: f32 },
+    PowerLaw { min: f32, max: f32, alpha: f32 },
+    Kroupa { min: f32, max: f32, break_mass: f32 },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MassDistributionError {
+    /// Power law bounds that aren't finite with `0 < min <= max`
+    InvalidRange,
+    InvalidSlope,
+    InvalidBreakMass,
+    /// Mean or median that isn't finite and positive
+    InvalidMean,
+    /// Spread that isn't finite and non-negative, or reaches 100% for uniform
+    InvalidSpread,
+}
+
+impl fmt::Display for MassDistributionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MassDistributionError::InvalidRange => {
+                write!(f, "Mass range must be finite with 0 < min <= max")
+            }
+            MassDistributionError::InvalidSlope => write!(f, "Power law slope must be finite"),
+            MassDistributionError::InvalidBreakMass => write!(f, "Break mass must be finite"),
+            MassDistributionError::InvalidMean => {
+                write!(f, "Mean mass must be finite and positive")
+            }
+            MassDistributionError::InvalidSpread => write!(
+                f,
+                "Mass spread must be finite and non-negative, and below 100% for uniform masses"
+            ),
+        }
+    }
+}
+
+/// How particle masses are drawn when generating a scene.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MassDistribution {
+    law: MassLaw,
+}
+
+impl MassDistribution {
+    /// Uniform within `deviation` percent of `mean`, as `Particle::new_rand` does.
+    pub fn uniform(mean: f32, deviation: f32) -> Result<MassDistribution, MassDistributionError> {
+        check_mean(mean)?;
+        if !(0.0..100.0).contains(&deviation) {
+            return Err(MassDistributionError::InvalidSpread);
+        }
+
+        Ok(MassDistribution::uniform_unchecked(mean, deviation))
+    }
+
+    /// `uniform` without validation, for `Particle::new_rand` and the default
+    /// generation options, which have always accepted any mass and deviation.
+    pub(crate) fn uniform_unchecked(mean: f32, deviation: f32) -> MassDistribution {
+        MassDistribution {
+            law: MassLaw::Uniform { mean, deviation },
+        }
+    }
+
+    /// Gaussian, redrawn until positive.
+    pub fn normal(mean: f32, std_dev: f32) -> Result<MassDistribution, MassDistributionError> {
+        check_mean(mean)?;
+        check_spread(std_dev)?;
+
+        Ok(MassDistribution {
+            law: MassLaw::Normal { mean, std_dev },
+        })
+    }
+
+    /// Log-normal with the given median and standard deviation of `ln(m)`.
+    pub fn log_normal(median: f32, sigma: f32) -> Result<MassDistribution, MassDistributionError> {
+        check_mean(median)?;
+        check_spread(sigma)?;
+
+        Ok(MassDistribution {
+            law: MassLaw::LogNormal { median, sigma },
+        })
+    }
+
+    /// Power law dN/dm ∝ m^-alpha between `min` and `max`.
+    pub fn power_law(
+        min: f32,
+        max: f32,
+        alpha: f32,
+    ) -> Result<MassDistribution, MassDistributionError> {
+        check_range(min, max)?;
+        if !alpha.is_finite() {
+            return Err(MassDistributionError::InvalidSlope);
+        }
+
+        Ok(MassDistribution {
+            law: MassLaw::PowerLaw { min, max, alpha },
+        })
+    }
+
+    pub fn salpeter(min: f32, max: f32) -> Result<MassDistribution, MassDistributionError> {
+        MassDistribution::power_law(min, max, SALPETER_ALPHA)
+    }
+
+    /// Broken power law with slope 1.3 below `break_mass` and 2.3 above,
+    /// continuous at the break. The break is 0.5 solar masses in physical
+    /// units, and is moved into `[min, max]` when outside it.
+    pub fn kroupa(
+        min: f32,
+        max: f32,
+        break_mass: f32,
+    ) -> Result<MassDistribution, MassDistributionError> {
+        check_range(min, max)?;
+        if !break_mass.is_finite() {
+            return Err(MassDistributionError::InvalidBreakMass);
+        }
+
+        Ok(MassDistribution {
+            law: MassLaw::Kroupa {
+                min,
+                max,
+                break_mass,
+            },
+        })
+    }
+
+    pub fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> f32 {
+        match self.law {
+            MassLaw::Uniform { mean, deviation } => {
+                ((rng.gen::<f32>() - 0.5) * deviation / 100.0 * mean * 2.0) + mean
+            }
+            MassLaw::Normal { mean, std_dev } => (0..MAX_NORMAL_REDRAWS)
+                .map(|_| mean + std_dev * standard_normal(rng))
+                .find(|&m| m > 0.0)
+                .unwrap_or(mean),
+            MassLaw::LogNormal { median, sigma } => median * (sigma * standard_normal(rng)).exp(),
+            MassLaw::PowerLaw { min, max, alpha } => {
+                sample_power_law(min, max, alpha, rng.gen::<f32>())
+            }
+            MassLaw::Kroupa {
+                min,
+                max,
+                break_mass,
+            } => {
+                let break_mass = break_mass.clamp(min, max);
+
+                // Relative weight of each segment with f(m) = m^-a1 below the
+                // break and b^(a2 - a1) m^-a2 above, which joins continuously
+                let low = power_law_integral(min, break_mass, KROUPA_ALPHA_LOW);
+                let high = break_mass.powf(KROUPA_ALPHA_HIGH - KROUPA_ALPHA_LOW)
+                    * power_law_integral(break_mass, max, KROUPA_ALPHA_HIGH);
+
+                if rng.gen::<f32>() * (low + high) < low {
+                    sample_power_law(min, break_mass, KROUPA_ALPHA_LOW, rng.gen::<f32>())
+                } else {
+                    sample_power_law(break_mass, max, KROUPA_ALPHA_HIGH, rng.gen::<f32>())
+                }
+            }
+        }
+    }
+}
+
+/// How a particle's diameter scales with its mass relative to a reference
+/// particle of `mass` and `diameter`.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum DiameterLaw {
+    /// Diameter proportional to mass
+    #[default]
+    Linear,
+    /// Disc area proportional to mass
+    Area,
+    /// Constant density sphere, diameter ∝ m^(1/3)
+    Density,
+}
+
+impl DiameterLaw {
+    pub fn diameter(&self, mass: f32, reference_mass: f32, reference_diameter: f32) -> f32 {
+        let ratio = mass / reference_mass;
+        match self {
+            DiameterLaw::Linear => reference_diameter * ratio,
+            DiameterLaw::Area => reference_diameter * ratio.sqrt(),
+            DiameterLaw::Density => reference_diameter * ratio.cbrt(),
+        }
+    }
+}
+
+fn check_mean(mean: f32) -> Result<(), MassDistributionError> {
+    if mean > 0.0 && mean.is_finite() {
+        Ok(())
+    } else {
+        Err(MassDistributionError::InvalidMean)
+    }
+}
+
+fn check_spread(spread: f32) -> Result<(), MassDistributionError> {
+    if spread >= 0.0 && spread.is_finite() {
+        Ok(())
+    } else {
+        Err(MassDistributionError::InvalidSpread)
+    }
+}
+
+/// Power laws diverge or sample outside their bounds at `min <= 0`.
+fn check_range(min: f32, max: f32) -> Result<(), MassDistributionError> {
+    if min > 0.0 && min <= max && max.is_finite() {
+        Ok(())
+    } else {
+        Err(MassDistributionError::InvalidRange)
+    }
+}
+
+/// Inverse transform sampling of dN/dm ∝ m^-alpha on [min, max].
+fn sample_power_law(min: f32, max: f32, alpha: f32, u: f32) -> f32 {
+    if (alpha - 1.0).abs() < 1e-6 {
+        return min * (max / min).powf(u);
+    }
+
+    let k = 1.0 - alpha;
+    let lo = min.powf(k);
+    let hi = max.powf(k);
+    (lo + u * (hi - lo)).powf(1.0 / k)
+}
+
+fn power_law_integral(min: f32, max: f32, alpha: f32) -> f32 {
+    if (alpha - 1.0).abs() < 1e-6 {
+        return (max / min).ln();
+    }
+
+    let k = 1.0 - alpha;
+    (max.powf(k) - min.powf(k)) / k
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn samples(distribution: MassDistribution) -> Vec<f32> {
+        let mut rng = StdRng::seed_from_u64(42);
+        (0..20_000).map(|_| distribution.sample(&mut rng)).collect()
+    }
+
+    fn median(values: &mut [f32]) -> f32 {
+        values.sort_by(|a, b| a.total_cmp(b));
+        values[values.len() / 2]
+    }
+
+    #[test]
+    fn test_uniform_and_normal_stay_positive_around_mean() {
+        let uniform = samples(MassDistribution::uniform(100.0, 25.0).unwrap());
+        assert!(uniform.iter().all(|&m| (75.0..=125.0).contains(&m)));
+
+        let normal = samples(MassDistribution::normal(100.0, 80.0).unwrap());
+        assert!(normal.iter().all(|&m| m > 0.0));
+    }
+
+    #[test]
+    fn test_log_normal_median() {
+        let mut values = samples(MassDistribution::log_normal(10.0, 1.0).unwrap());
+        assert!((median(&mut values) - 10.0).abs() < 0.5);
+    }
+
+    #[test]
+    fn test_power_laws_respect_bounds_and_favor_low_masses() {
+        for distribution in [
+            MassDistribution::salpeter(0.1, 100.0).unwrap(),
+            MassDistribution::kroupa(0.08, 100.0, 0.5).unwrap(),
+        ] {
+            let mut values = samples(distribution);
+            assert!(values.iter().all(|&m| (0.08..=100.0).contains(&m)));
+            assert!(median(&mut values) < 1.0);
+        }
+    }
+
+    #[test]
+    fn test_invalid_power_law_parameters_are_rejected() {
+        for (min, max) in [(0.0, 10.0), (-1.0, 10.0), (5.0, 1.0), (1.0, f32::INFINITY)] {
+            assert_eq!(
+                MassDistribution::salpeter(min, max),
+                Err(MassDistributionError::InvalidRange)
+            );
+            assert_eq!(
+                MassDistribution::kroupa(min, max, 0.5),
+                Err(MassDistributionError::InvalidRange)
+            );
+        }
+        assert_eq!(
+            MassDistribution::power_law(0.1, 10.0, f32::NAN),
+            Err(MassDistributionError::InvalidSlope)
+        );
+        for break_mass in [f32::NAN, f32::INFINITY] {
+            assert_eq!(
+                MassDistribution::kroupa(0.08, 100.0, break_mass),
+                Err(MassDistributionError::InvalidBreakMass)
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_mean_and_spread_are_rejected() {
+        for mean in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+            assert_eq!(
+                MassDistribution::uniform(mean, 10.0),
+                Err(MassDistributionError::InvalidMean)
+            );
+            assert_eq!(
+                MassDistribution::normal(mean, 0.0),
+                Err(MassDistributionError::InvalidMean)
+            );
+            assert_eq!(
+                MassDistribution::log_normal(mean, 1.0),
+                Err(MassDistributionError::InvalidMean)
+            );
+        }
+        for spread in [-1.0, f32::NAN, f32::INFINITY] {
+            assert_eq!(
+                MassDistribution::uniform(1.0, spread),
+                Err(MassDistributionError::InvalidSpread)
+            );
+            assert_eq!(
+                MassDistribution::normal(1.0, spread),
+                Err(MassDistributionError::InvalidSpread)
+            );
+            assert_eq!(
+                MassDistribution::log_normal(1.0, spread),
+                Err(MassDistributionError::InvalidSpread)
+            );
+        }
+        assert_eq!(
+            MassDistribution::uniform(1.0, 100.0),
+            Err(MassDistributionError::InvalidSpread)
+        );
+    }
+
+    #[test]
+    fn test_diameter_laws() {
+        assert_eq!(DiameterLaw::Linear.diameter(8.0, 1.0, 2.0), 16.0);
+        assert_eq!(DiameterLaw::Area.diameter(4.0, 1.0, 2.0), 4.0);
+        assert!((DiameterLaw::Density.diameter(8.0, 1.0, 2.0) - 4.0).abs() < 1e-5);
+    }
+}
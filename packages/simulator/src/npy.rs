@@ -0,0 +1,267 @@
+use std::fmt;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::simulation::Simulation;
+use crate::utils::binary_utils::ByteWriter;
+use crate::utils::zip_utils::stored_zip;
+pub use crate::utils::zip_utils::ZipError;
+
+const MAGIC: &[u8; 6] = b"\x93NUMPY";
+
+/// The header is padded with spaces so the data starts on this boundary.
+const ALIGNMENT: usize = 64;
+
+/// Arrays `to_npy` can export, named after the fields they come from.
+pub const NPY_ARRAYS: [&str; 10] = [
+    "ids",
+    "positions_x",
+    "positions_y",
+    "velocities_x",
+    "velocities_y",
+    "masses",
+    "diameters",
+    "colors",
+    "densities",
+    "time",
+];
+
+/// Python tuple literal for `shape`; one-element tuples need the comma.
+fn shape_tuple(shape: &[usize]) -> String {
+    match shape {
+        [n] => format!("({},)", n),
+        _ => {
+            let dims: Vec<String> = shape.iter().map(|n| n.to_string()).collect();
+            format!("({})", dims.join(", "))
+        }
+    }
+}
+
+/// Version 1.0 `.npy` header for a C-ordered array. `descr` is the NumPy
+/// type string, e.g. `<f4`.
+fn npy_header(descr: &str, shape: &[usize]) -> ByteWriter {
+    let mut header = format!(
+        "{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}",
+        descr,
+        shape_tuple(shape)
+    );
+    let unpadded = MAGIC.len() + 2 + 2 + header.len() + 1;
+    header.push_str(&" ".repeat((ALIGNMENT - unpadded % ALIGNMENT) % ALIGNMENT));
+    header.push('\n');
+
+    let mut writer = ByteWriter::new();
+    writer.write_bytes(MAGIC);
+    writer.write_bytes(&[1, 0]);
+    writer.write_u16(header.len() as u16);
+    writer.write_bytes(header.as_bytes());
+    writer
+}
+
+pub fn f32_npy(values: &[f32], shape: &[usize]) -> Vec<u8> {
+    let mut writer = npy_header("<f4", shape);
+    writer.write_f32_slice(values);
+    writer.into_bytes()
+}
+
+pub fn f64_npy(values: &[f64], shape: &[usize]) -> Vec<u8> {
+    let mut writer = npy_header("<f8", shape);
+    writer.write_f64_slice(values);
+    writer.into_bytes()
+}
+
+pub fn i32_npy(values: &[i32], shape: &[usize]) -> Vec<u8> {
+    let mut writer = npy_header("<i4", shape);
+    writer.write_i32_slice(values);
+    writer.into_bytes()
+}
+
+impl Simulation {
+    /// One array as a `.npy` file, or `None` for a name not in
+    /// `NPY_ARRAYS`. Colors have shape `(count, 3)` and the time is a
+    /// 0-d array.
+    pub fn to_npy(&self, name: &str) -> Option<Vec<u8>> {
+        let n = self.count;
+        let npy = match name {
+            "ids" => i32_npy(&self.ids, &[n]),
+            "positions_x" => f32_npy(&self.positions_x, &[n]),
+            "positions_y" => f32_npy(&self.positions_y, &[n]),
+            "velocities_x" => f32_npy(&self.velocities_x, &[n]),
+            "velocities_y" => f32_npy(&self.velocities_y, &[n]),
+            "masses" => f32_npy(&self.masses, &[n]),
+            "diameters" => f32_npy(&self.diameters, &[n]),
+            "colors" => f32_npy(&self.colors, &[n, 3]),
+            "densities" => f32_npy(&self.densities, &[n]),
+            "time" => f64_npy(&[self.time], &[]),
+            _ => return None,
+        };
+        Some(npy)
+    }
+
+    /// Every array in `NPY_ARRAYS`, as `numpy.load` expects an `.npz`.
+    pub fn to_npz(&self) -> Result<Vec<u8>, ZipError> {
+        let entries: Vec<(String, Vec<u8>)> = NPY_ARRAYS
+            .iter()
+            .map(|name| (format!("{}.npy", name), self.to_npy(name).unwrap()))
+            .collect();
+        stored_zip(&entries)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FrameSizeMismatch {
+    pub expected: usize,
+    pub found: usize,
+}
+
+impl fmt::Display for FrameSizeMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Trajectory frames have {} particles, not {}",
+            self.expected, self.found
+        )
+    }
+}
+
+/// Positions and velocities of successive frames, exported as
+/// `(frames, count)` arrays alongside the frame times and particle IDs.
+/// Every frame must hold the same particles.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone, Default)]
+pub struct TrajectoryStack {
+    ids: Vec<i32>,
+    times: Vec<f64>,
+    positions_x: Vec<f32>,
+    positions_y: Vec<f32>,
+    velocities_x: Vec<f32>,
+    velocities_y: Vec<f32>,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl TrajectoryStack {
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new() -> TrajectoryStack {
+        TrajectoryStack::default()
+    }
+
+    pub fn frames(&self) -> usize {
+        self.times.len()
+    }
+}
+
+impl TrajectoryStack {
+    pub fn to_npz(&self) -> Result<Vec<u8>, ZipError> {
+        let shape = [self.frames(), self.ids.len()];
+        stored_zip(&[
+            ("ids.npy", i32_npy(&self.ids, &shape[1..])),
+            ("times.npy", f64_npy(&self.times, &shape[..1])),
+            ("positions_x.npy", f32_npy(&self.positions_x, &shape)),
+            ("positions_y.npy", f32_npy(&self.positions_y, &shape)),
+            ("velocities_x.npy", f32_npy(&self.velocities_x, &shape)),
+            ("velocities_y.npy", f32_npy(&self.velocities_y, &shape)),
+        ])
+    }
+
+    /// Appends the simulation's current state as the next frame. The first
+    /// frame fixes the particle IDs.
+    pub fn push(&mut self, sim: &Simulation) -> Result<(), FrameSizeMismatch> {
+        if self.frames() == 0 {
+            self.ids = sim.ids.clone();
+        } else if sim.count != self.ids.len() {
+            return Err(FrameSizeMismatch {
+                expected: self.ids.len(),
+                found: sim.count,
+            });
+        }
+
+        self.times.push(sim.time);
+        self.positions_x.extend_from_slice(&sim.positions_x);
+        self.positions_y.extend_from_slice(&sim.positions_y);
+        self.velocities_x.extend_from_slice(&sim.velocities_x);
+        self.velocities_y.extend_from_slice(&sim.velocities_y);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::particle::Particle;
+    use crate::vector2::Vector2;
+
+    fn simulation(count: usize) -> Simulation {
+        Simulation::new(
+            (0..count)
+                .map(|i| {
+                    Particle::new(
+                        1.0,
+                        1.0,
+                        Vector2::new(i as f32, 0.0),
+                        Vector2::new(0.0, 1.0),
+                        [255.0, 255.0, 255.0],
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    fn header_text(npy: &[u8]) -> &str {
+        let len = u16::from_le_bytes([npy[8], npy[9]]) as usize;
+        std::str::from_utf8(&npy[10..10 + len]).unwrap()
+    }
+
+    #[test]
+    fn test_npy_layout() {
+        let npy = f32_npy(&[1.0, 2.0], &[2]);
+
+        assert_eq!(&npy[..8], b"\x93NUMPY\x01\x00");
+        let header = header_text(&npy);
+        assert!(header.starts_with("{'descr': '<f4', 'fortran_order': False, 'shape': (2,), }"));
+        assert!(header.ends_with('\n'));
+        assert_eq!((10 + header.len()) % ALIGNMENT, 0);
+        assert_eq!(&npy[npy.len() - 8..], &[0, 0, 0x80, 0x3f, 0, 0, 0, 0x40]);
+    }
+
+    #[test]
+    fn test_simulation_arrays() {
+        let sim = simulation(3);
+
+        let colors = sim.to_npy("colors").unwrap();
+        assert!(header_text(&colors).contains("'shape': (3, 3)"));
+        let time = sim.to_npy("time").unwrap();
+        assert!(header_text(&time).contains("'descr': '<f8'"));
+        assert!(header_text(&time).contains("'shape': ()"));
+        assert!(header_text(&sim.to_npy("ids").unwrap()).contains("'<i4'"));
+        assert_eq!(sim.to_npy("pressure"), None);
+
+        let npz = sim.to_npz().unwrap();
+        assert_eq!(&npz[..4], b"PK\x03\x04");
+        assert!(npz.windows(16).any(|name| name == b"velocities_y.npy"));
+    }
+
+    #[test]
+    fn test_trajectory_stack() {
+        let mut sim = simulation(2);
+        let mut stack = TrajectoryStack::new();
+
+        for _ in 0..3 {
+            stack.push(&sim).unwrap();
+            sim.step(Vector2::new(10.0, 10.0), 1.0, 0.1, 0.5);
+        }
+
+        assert_eq!(stack.frames(), 3);
+        assert_eq!(stack.times, vec![0.0, 0.5, 1.0]);
+        assert_eq!(stack.positions_x.len(), 6);
+        assert_eq!(&stack.positions_x[..2], &[0.0, 1.0]);
+        assert_eq!(
+            stack.push(&simulation(5)).unwrap_err(),
+            FrameSizeMismatch {
+                expected: 2,
+                found: 5
+            }
+        );
+        let npz = stack.to_npz().unwrap();
+        assert!(npz.windows(15).any(|name| name == b"positions_x.npy"));
+    }
+}
@@ -0,0 +1,228 @@
+use serde::Serialize;
+
+use crate::simulation::Simulation;
+use crate::utils::orbit_utils::{elements_from_state, orbital_period};
+use crate::vector2::Vector2;
+
+/// Nearest neighbors considered when looking for a particle's most-bound
+/// partner. A heavier body farther out than these is never picked, even if
+/// the particle is more tightly bound to it.
+const BOUND_CANDIDATES: usize = 8;
+
+/// Osculating elements of one particle's orbit relative to `primary`.
+#[derive(Debug, Copy, Clone, Serialize)]
+pub struct OrbitReport {
+    pub index: usize,
+    pub primary: usize,
+    pub semi_major_axis: f32,
+    pub eccentricity: f32,
+    pub argument_of_periapsis: f32,
+    pub period: Option<f32>,
+    pub bound: bool,
+}
+
+/// Two particles that are each other's most-bound neighbor with negative
+/// two-body energy. `primary` is the heavier of the pair.
+#[derive(Debug, Copy, Clone, Serialize)]
+pub struct Binary {
+    pub primary: usize,
+    pub secondary: usize,
+    pub semi_major_axis: f32,
+    pub eccentricity: f32,
+    pub period: f32,
+}
+
+impl Simulation {
+    /// Specific two-body energy of the pair, negative when bound.
+    fn pair_energy(&self, i: usize, j: usize, gravity: f32) -> f64 {
+        let dx = (self.positions_x[i] - self.positions_x[j]) as f64;
+        let dy = (self.positions_y[i] - self.positions_y[j]) as f64;
+        let dvx = (self.velocities_x[i] - self.velocities_x[j]) as f64;
+        let dvy = (self.velocities_y[i] - self.velocities_y[j]) as f64;
+        let mu = gravity as f64 * (self.masses[i] as f64 + self.masses[j] as f64);
+
+        let r = (dx * dx + dy * dy).sqrt();
+        if r == 0.0 {
+            return f64::INFINITY;
+        }
+
+        (dvx * dvx + dvy * dvy) / 2.0 - mu / r
+    }
+
+    /// Orbit of `index` around `primary`, or `None` when the two sit at the
+    /// same position and have no orbit.
+    pub fn orbit_about(&self, index: usize, primary: usize, gravity: f32) -> Option<OrbitReport> {
+        let mu = gravity as f64 * (self.masses[index] as f64 + self.masses[primary] as f64);
+        let rx = (self.positions_x[index] - self.positions_x[primary]) as f64;
+        let ry = (self.positions_y[index] - self.positions_y[primary]) as f64;
+        if rx == 0.0 && ry == 0.0 {
+            return None;
+        }
+
+        let (a, e, omega) = elements_from_state(
+            rx,
+            ry,
+            (self.velocities_x[index] - self.velocities_x[primary]) as f64,
+            (self.velocities_y[index] - self.velocities_y[primary]) as f64,
+            mu,
+        );
+        let bound = a > 0.0 && e < 1.0;
+
+        Some(OrbitReport {
+            index,
+            primary,
+            semi_major_axis: a as f32,
+            eccentricity: e as f32,
+            argument_of_periapsis: omega as f32,
+            period: if bound {
+                Some(orbital_period(a, mu) as f32)
+            } else {
+                None
+            },
+            bound,
+        })
+    }
+
+    /// Orbits of every particle except `primary` around `primary`, leaving
+    /// out particles at the same position.
+    pub fn orbits_about(&self, primary: usize, gravity: f32) -> Vec<OrbitReport> {
+        (0..self.count)
+            .filter(|&i| i != primary)
+            .filter_map(|i| self.orbit_about(i, primary, gravity))
+            .collect()
+    }
+
+    /// For every particle, the nearby particle it is most tightly bound to,
+    /// searched among its `BOUND_CANDIDATES` nearest neighbors in a
+    /// `QuadTree` rather than across the whole simulation.
+    pub fn most_bound_neighbors(&self, gravity: f32) -> Vec<Option<usize>> {
+        let tree = self.tree();
+
+        (0..self.count)
+            .map(|i| {
+                let p_pos = Vector2::new(self.positions_x[i], self.positions_y[i]);
+                tree.nearest_neighbors(
+                    p_pos,
+                    BOUND_CANDIDATES,
+                    Some(i),
+                    &self.positions_x,
+                    &self.positions_y,
+                )
+                .into_iter()
+                .map(|j| (j, self.pair_energy(i, j, gravity)))
+                .filter(|(_, energy)| energy.is_finite())
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(j, _)| j)
+            })
+            .collect()
+    }
+
+    /// Orbit of each particle relative to its most-bound neighbor. Particles
+    /// without a usable neighbor are left out.
+    pub fn orbits_about_most_bound(&self, gravity: f32) -> Vec<OrbitReport> {
+        self.most_bound_neighbors(gravity)
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, neighbor)| neighbor.and_then(|j| self.orbit_about(i, j, gravity)))
+            .collect()
+    }
+
+    pub fn find_binaries(&self, gravity: f32) -> Vec<Binary> {
+        let neighbors = self.most_bound_neighbors(gravity);
+        let mut binaries = Vec::new();
+
+        for (i, neighbor) in neighbors.iter().enumerate() {
+            let j = match *neighbor {
+                Some(j) if j > i && neighbors[j] == Some(i) => j,
+                _ => continue,
+            };
+
+            let (primary, secondary) = if self.masses[i] >= self.masses[j] {
+                (i, j)
+            } else {
+                (j, i)
+            };
+            let report = match self.orbit_about(secondary, primary, gravity) {
+                Some(report) => report,
+                None => continue,
+            };
+
+            if let Some(period) = report.period {
+                binaries.push(Binary {
+                    primary,
+                    secondary,
+                    semi_major_axis: report.semi_major_axis,
+                    eccentricity: report.eccentricity,
+                    period,
+                });
+            }
+        }
+
+        binaries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::orbital_elements::OrbitalElements;
+    use crate::particle::Particle;
+    use crate::simulation::Simulation;
+    use crate::vector2::Vector2;
+
+    fn star(position: Vector2, velocity: Vector2, mass: f32) -> Particle {
+        Particle::new(mass, 1.0, position, velocity, [255.0, 255.0, 255.0])
+    }
+
+    #[test]
+    fn test_orbits_about_primary_recover_elements() {
+        let sun = star(Vector2::new(500.0, 500.0), Vector2::new(0.0, 0.0), 1.0e6);
+        let elements = OrbitalElements::new(100.0, 0.3, 1.0, 2.0, 1.0, 1.0);
+        let planet = Particle::from_orbital_elements(&sun, &elements, 1.0).unwrap();
+
+        let sim = Simulation::new(vec![sun, planet]);
+        let reports = sim.orbits_about(0, 1.0);
+
+        assert_eq!(reports.len(), 1);
+        let report = reports[0];
+        assert!(report.bound);
+        assert!((report.semi_major_axis - 100.0).abs() < 0.1);
+        assert!((report.eccentricity - 0.3).abs() < 1e-3);
+        assert!((report.argument_of_periapsis - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_find_binaries() {
+        let gravity = 1.0;
+        // Tight binary far away from a lone, fast-moving particle
+        let a = star(Vector2::new(100.0, 100.0), Vector2::new(0.0, -0.5), 100.0);
+        let b = star(Vector2::new(110.0, 100.0), Vector2::new(0.0, 0.5), 100.0);
+        let loner = star(Vector2::new(900.0, 900.0), Vector2::new(50.0, 0.0), 1.0);
+
+        let sim = Simulation::new(vec![a, b, loner]);
+        let binaries = sim.find_binaries(gravity);
+
+        assert_eq!(binaries.len(), 1);
+        assert_eq!(binaries[0].primary + binaries[0].secondary, 1);
+        assert!(binaries[0].eccentricity < 1.0);
+
+        let orbits = sim.orbits_about_most_bound(gravity);
+        assert_eq!(orbits.len(), 3);
+        assert!(!orbits[2].bound);
+    }
+
+    #[test]
+    fn test_coincident_particles_have_no_orbit() {
+        let sun = star(Vector2::new(500.0, 500.0), Vector2::new(0.0, 0.0), 1.0e6);
+        let twin = star(Vector2::new(500.0, 500.0), Vector2::new(1.0, 0.0), 1.0);
+        let planet = star(Vector2::new(600.0, 500.0), Vector2::new(0.0, 100.0), 1.0);
+
+        let sim = Simulation::new(vec![sun, twin, planet]);
+
+        assert!(sim.orbit_about(1, 0, 1.0).is_none());
+        assert!(sim.orbit_about(0, 0, 1.0).is_none());
+        let reports = sim.orbits_about(0, 1.0);
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].index, 2);
+        assert!(reports[0].semi_major_axis.is_finite());
+    }
+}
@@ -0,0 +1,234 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::particle::Particle;
+use crate::utils::orbit_utils::solve_kepler;
+use crate::vector2::Vector2;
+
+/// Elements that don't describe a bound orbit.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OrbitalElementsError {
+    InvalidSemiMajorAxis,
+    InvalidEccentricity,
+}
+
+impl fmt::Display for OrbitalElementsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OrbitalElementsError::InvalidSemiMajorAxis => {
+                write!(f, "Semi-major axis must be positive and finite")
+            }
+            OrbitalElementsError::InvalidEccentricity => {
+                write!(f, "Eccentricity must be at least 0 and below 1")
+            }
+        }
+    }
+}
+
+/// Keplerian elements of a body orbiting a central mass in the simulation plane.
+/// Angles are in radians, `argument_of_periapsis` is measured from the +x axis.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct OrbitalElements {
+    pub semi_major_axis: f32,
+    pub eccentricity: f32,
+    pub argument_of_periapsis: f32,
+    pub mean_anomaly: f32,
+    pub mass: f32,
+    pub diameter: f32,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl OrbitalElements {
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new(
+        semi_major_axis: f32,
+        eccentricity: f32,
+        argument_of_periapsis: f32,
+        mean_anomaly: f32,
+        mass: f32,
+        diameter: f32,
+    ) -> OrbitalElements {
+        OrbitalElements {
+            semi_major_axis,
+            eccentricity,
+            argument_of_periapsis,
+            mean_anomaly,
+            mass,
+            diameter,
+        }
+    }
+}
+
+impl OrbitalElements {
+    /// Checks the elements describe a bound orbit: a positive, finite
+    /// semi-major axis and an eccentricity in [0, 1).
+    pub fn validate(&self) -> Result<(), OrbitalElementsError> {
+        if !(self.semi_major_axis > 0.0 && self.semi_major_axis.is_finite()) {
+            return Err(OrbitalElementsError::InvalidSemiMajorAxis);
+        }
+        if !(0.0..1.0).contains(&self.eccentricity) {
+            return Err(OrbitalElementsError::InvalidEccentricity);
+        }
+        Ok(())
+    }
+
+    /// Position and velocity relative to the central body, for a standard
+    /// gravitational parameter `mu = G * (M + m)`.
+    pub fn relative_state(&self, mu: f64) -> Result<(Vector2, Vector2), OrbitalElementsError> {
+        self.validate()?;
+
+        let a = self.semi_major_axis as f64;
+        let e = self.eccentricity as f64;
+        let omega = self.argument_of_periapsis as f64;
+
+        let eccentric_anomaly = solve_kepler(self.mean_anomaly as f64, e);
+        let (sin_e, cos_e) = eccentric_anomaly.sin_cos();
+        let b_over_a = (1.0 - e * e).sqrt();
+
+        // Perifocal frame: periapsis along +x
+        let x = a * (cos_e - e);
+        let y = a * b_over_a * sin_e;
+
+        let r = a * (1.0 - e * cos_e);
+        let speed_factor = (mu * a).sqrt() / r;
+        let vx = -speed_factor * sin_e;
+        let vy = speed_factor * b_over_a * cos_e;
+
+        let (sin_w, cos_w) = omega.sin_cos();
+        let position = Vector2::new(
+            (x * cos_w - y * sin_w) as f32,
+            (x * sin_w + y * cos_w) as f32,
+        );
+        let velocity = Vector2::new(
+            (vx * cos_w - vy * sin_w) as f32,
+            (vx * sin_w + vy * cos_w) as f32,
+        );
+
+        Ok((position, velocity))
+    }
+}
+
+/// `central` with a body on each orbit in `elements`, central body first.
+/// The central body recoils against the orbiting bodies so the system keeps
+/// the central body's original momentum instead of drifting off.
+pub fn planetary_system(
+    central: Particle,
+    elements: &[OrbitalElements],
+    gravity: f32,
+) -> Result<Vec<Particle>, OrbitalElementsError> {
+    let mut particles = vec![central];
+    let mut momentum = (0.0f64, 0.0f64);
+    for e in elements {
+        let body = Particle::from_orbital_elements(&central, e, gravity)?;
+        let relative_velocity = body.velocity - central.velocity;
+        momentum.0 += body.mass as f64 * relative_velocity.x as f64;
+        momentum.1 += body.mass as f64 * relative_velocity.y as f64;
+        particles.push(body);
+    }
+
+    if central.mass > 0.0 {
+        let m = central.mass as f64;
+        particles[0].velocity =
+            central.velocity - Vector2::new((momentum.0 / m) as f32, (momentum.1 / m) as f32);
+    }
+    Ok(particles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_circular_orbit_state() {
+        let elements = OrbitalElements::new(10.0, 0.0, 0.0, 0.0, 1.0, 1.0);
+        let (position, velocity) = elements.relative_state(100.0).unwrap();
+
+        assert_relative_eq!(position.x, 10.0, epsilon = 1e-4);
+        assert_relative_eq!(position.y, 0.0, epsilon = 1e-4);
+        assert_relative_eq!(velocity.x, 0.0, epsilon = 1e-4);
+        // v = sqrt(mu / r)
+        assert_relative_eq!(velocity.y, 10.0_f32.sqrt(), epsilon = 1e-4);
+    }
+
+    #[test]
+    fn test_eccentric_orbit_at_periapsis_and_apoapsis() {
+        let a = 10.0;
+        let e = 0.5;
+        let mu = 100.0;
+
+        let periapsis = OrbitalElements::new(a, e, 0.0, 0.0, 1.0, 1.0);
+        let (position, velocity) = periapsis.relative_state(mu as f64).unwrap();
+        assert_relative_eq!(position.x, a * (1.0 - e), epsilon = 1e-4);
+        // vis-viva: v^2 = mu * (2 / r - 1 / a)
+        let expected = (mu * (2.0 / (a * (1.0 - e)) - 1.0 / a)).sqrt();
+        assert_relative_eq!(velocity.magnitude(), expected, epsilon = 1e-4);
+
+        let apoapsis = OrbitalElements::new(a, e, 0.0, std::f32::consts::PI, 1.0, 1.0);
+        let (position, _) = apoapsis.relative_state(mu as f64).unwrap();
+        assert_relative_eq!(position.x, -a * (1.0 + e), epsilon = 1e-4);
+    }
+
+    #[test]
+    fn test_argument_of_periapsis_rotates_orbit() {
+        let elements = OrbitalElements::new(10.0, 0.0, std::f32::consts::FRAC_PI_2, 0.0, 1.0, 1.0);
+        let (position, velocity) = elements.relative_state(100.0).unwrap();
+
+        assert_relative_eq!(position.x, 0.0, epsilon = 1e-4);
+        assert_relative_eq!(position.y, 10.0, epsilon = 1e-4);
+        assert!(velocity.x < 0.0);
+    }
+    #[test]
+    fn test_unbound_or_degenerate_elements_are_rejected() {
+        for a in [0.0, -10.0, f32::NAN, f32::INFINITY] {
+            let elements = OrbitalElements::new(a, 0.1, 0.0, 0.0, 1.0, 1.0);
+            assert_eq!(
+                elements.relative_state(100.0),
+                Err(OrbitalElementsError::InvalidSemiMajorAxis)
+            );
+        }
+        for e in [-0.1, 1.0, 1.5, f32::NAN] {
+            let elements = OrbitalElements::new(10.0, e, 0.0, 0.0, 1.0, 1.0);
+            assert_eq!(
+                elements.relative_state(100.0),
+                Err(OrbitalElementsError::InvalidEccentricity)
+            );
+        }
+    }
+
+    #[test]
+    fn test_planetary_system_keeps_central_momentum() {
+        let central = Particle::new(
+            1000.0,
+            1.0,
+            Vector2::new(50.0, 50.0),
+            Vector2::new(0.5, 0.0),
+            [255.0, 255.0, 255.0],
+        );
+        let elements = [
+            OrbitalElements::new(10.0, 0.1, 0.0, 0.0, 5.0, 1.0),
+            OrbitalElements::new(20.0, 0.3, 1.0, 2.0, 20.0, 1.0),
+        ];
+
+        let particles = planetary_system(central, &elements, 1.0).unwrap();
+
+        let total_mass: f32 = particles.iter().map(|p| p.mass).sum();
+        let momentum = particles.iter().fold(Vector2::new(0.0, 0.0), |sum, p| {
+            sum + p.velocity.scale(p.mass)
+        });
+        assert_relative_eq!(momentum.x, total_mass * 0.5, epsilon = 1e-3);
+        assert_relative_eq!(momentum.y, 0.0, epsilon = 1e-3);
+        assert_eq!(particles[0].position, central.position);
+        assert!(particles[0].velocity.y != 0.0);
+
+        let unbound = [OrbitalElements::new(10.0, 1.2, 0.0, 0.0, 1.0, 1.0)];
+        assert_eq!(
+            planetary_system(central, &unbound, 1.0).unwrap_err(),
+            OrbitalElementsError::InvalidEccentricity
+        );
+    }
+}
@@ -1,12 +1,15 @@
 use std::fmt::{self, Debug};
 
+use crate::mass_distribution::{DiameterLaw, MassDistribution};
+use crate::orbital_elements::{OrbitalElements, OrbitalElementsError};
 use crate::utils::calculation_utils::softened_gravitational_force;
 use crate::vector2::Vector2;
 use rand::prelude::*;
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 #[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct Particle {
     pub id: i32,
@@ -40,7 +43,7 @@ impl Particle {
         let mut rng = rand::thread_rng();
         let id: i32 = rng.gen();
 
-        return Particle {
+        Particle {
             id,
             mass,
             diameter,
@@ -49,7 +52,7 @@ impl Particle {
             color_r: color[0],
             color_g: color[1],
             color_b: color[2],
-        };
+        }
     }
     
     pub fn new_rand(
@@ -59,24 +62,44 @@ impl Particle {
         diameter: f32,
     ) -> Particle {
         let mut rng = rand::thread_rng();
-        let id = rng.gen::<i32>();
-
-        let m = ((rng.gen::<f32>() - 0.5) * mass_deviation / 100.0 * mass * 2.0) + mass;
-
-        let d = diameter * m / mass; //diameter based on mass
 
         let position = Vector2 {
             x: rng.gen::<f32>() * world_size.x,
             y: rng.gen::<f32>() * world_size.y,
         };
 
+        Particle::new_rand_at(
+            position,
+            &MassDistribution::uniform_unchecked(mass, mass_deviation),
+            DiameterLaw::Linear,
+            mass,
+            diameter,
+        )
+    }
+
+    /// Particle at rest at `position` with a mass drawn from `mass_distribution`,
+    /// a diameter scaled from the reference `mass`/`diameter` and a random color.
+    pub fn new_rand_at(
+        position: Vector2,
+        mass_distribution: &MassDistribution,
+        diameter_law: DiameterLaw,
+        mass: f32,
+        diameter: f32,
+    ) -> Particle {
+        let mut rng = rand::thread_rng();
+        let id = rng.gen::<i32>();
+
+        let m = mass_distribution.sample(&mut rng);
+
+        let d = diameter_law.diameter(m, mass, diameter);
+
         let velocity = Vector2 { x: 0.0, y: 0.0 };
 
         let color_r = rng.gen::<f32>() * 255.0;
         let color_g = rng.gen::<f32>() * 255.0;
         let color_b = rng.gen::<f32>() * 255.0;
 
-        return Particle {
+        Particle {
             id,
             mass: m,
             diameter: d,
@@ -85,11 +108,61 @@ impl Particle {
             color_r,
             color_g,
             color_b,
-        };
+        }
+    }
+
+    /// Places a body on the orbit described by `elements` around `central`,
+    /// inheriting the central body's velocity. `central` is left as it is,
+    /// so it gets no recoil; `orbital_elements::planetary_system` gives it
+    /// one that balances the momentum of every orbiting body.
+    pub fn from_orbital_elements(
+        central: &Particle,
+        elements: &OrbitalElements,
+        gravity: f32,
+    ) -> Result<Particle, OrbitalElementsError> {
+        let mut rng = rand::thread_rng();
+        let id = rng.gen::<i32>();
+
+        let mu = gravity as f64 * (central.mass as f64 + elements.mass as f64);
+        let (relative_position, relative_velocity) = elements.relative_state(mu)?;
+
+        Ok(Particle {
+            id,
+            mass: elements.mass,
+            diameter: elements.diameter,
+            position: central.position + relative_position,
+            velocity: central.velocity + relative_velocity,
+            color_r: rng.gen::<f32>() * 255.0,
+            color_g: rng.gen::<f32>() * 255.0,
+            color_b: rng.gen::<f32>() * 255.0,
+        })
+    }
+
+    /// Checks every value is one a simulation can run with, naming the
+    /// first problem found. Scene and CSV imports reject particles that fail.
+    pub fn validate(&self) -> Result<(), &'static str> {
+        let is_finite = |v: &Vector2| v.x.is_finite() && v.y.is_finite();
+
+        if !(self.mass >= 0.0 && self.mass.is_finite()) {
+            return Err("mass must be non-negative");
+        }
+        if !(self.diameter >= 0.0 && self.diameter.is_finite()) {
+            return Err("diameter must be non-negative");
+        }
+        if !is_finite(&self.position) || !is_finite(&self.velocity) {
+            return Err("position and velocity must be finite");
+        }
+        if ![self.color_r, self.color_g, self.color_b]
+            .iter()
+            .all(|c| (0.0..=255.0).contains(c))
+        {
+            return Err("color channels must be within 0-255");
+        }
+        Ok(())
     }
 
     pub fn next_position(&self) -> Vector2 {
-        return self.position + self.velocity;
+        self.position + self.velocity
     }
 
     pub fn next_velocity(
@@ -111,7 +184,7 @@ impl Particle {
             velocity = velocity + v;
         }
 
-        return velocity;
+        velocity
     }
 }
 
@@ -149,6 +222,26 @@ mod tests {
         assert!(particle.mass <= mass * (1.0 + mass_deviation / 100.0) + 1e-4);
     }
 
+    #[test]
+    fn test_particle_from_orbital_elements() {
+        let central = Particle::new(
+            1.0e10,
+            1.0,
+            Vector2::new(50.0, 50.0),
+            Vector2::new(1.0, 0.0),
+            [255.0, 255.0, 255.0],
+        );
+        let elements = OrbitalElements::new(10.0, 0.0, 0.0, 0.0, 1.0, 0.5);
+
+        let planet = Particle::from_orbital_elements(&central, &elements, 1.0).unwrap();
+
+        assert_eq!(planet.position, Vector2::new(60.0, 50.0));
+        assert!((planet.velocity.x - 1.0).abs() < 1e-4);
+        assert!((planet.velocity.y - 1.0e9_f32.sqrt()).abs() < 1.0);
+        assert_eq!(planet.mass, 1.0);
+        assert_eq!(planet.diameter, 0.5);
+    }
+
     #[test]
     fn test_next_position() {
         let particle = Particle {
@@ -0,0 +1,152 @@
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::mass_distribution::{DiameterLaw, MassDistribution};
+use crate::particle::Particle;
+use crate::spatial_distribution::{SpatialDistribution, SpatialDistributionError};
+use crate::vector2::Vector2;
+use crate::velocity_profile::VelocityProfile;
+
+/// Optional knobs for `generate_particles`. Anything left unset falls back
+/// to the uniform behaviour of `Particle::new_rand`.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Copy, Clone, Default)]
+pub struct GenerationOptions {
+    mass_distribution: Option<MassDistribution>,
+    diameter_law: DiameterLaw,
+    spatial_distribution: SpatialDistribution,
+    velocity_profile: VelocityProfile,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl GenerationOptions {
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
+    pub fn new() -> GenerationOptions {
+        GenerationOptions::default()
+    }
+
+    pub fn with_mass_distribution(
+        mut self,
+        mass_distribution: MassDistribution,
+    ) -> GenerationOptions {
+        self.mass_distribution = Some(mass_distribution);
+        self
+    }
+
+    pub fn with_diameter_law(mut self, diameter_law: DiameterLaw) -> GenerationOptions {
+        self.diameter_law = diameter_law;
+        self
+    }
+
+    pub fn with_spatial_distribution(
+        mut self,
+        spatial_distribution: SpatialDistribution,
+    ) -> GenerationOptions {
+        self.spatial_distribution = spatial_distribution;
+        self
+    }
+
+    pub fn with_velocity_profile(mut self, velocity_profile: VelocityProfile) -> GenerationOptions {
+        self.velocity_profile = velocity_profile;
+        self
+    }
+}
+
+/// Random particles spread over the world. `mass` and `diameter` describe the
+/// reference particle that the diameter law scales from. Fails when the
+/// spatial distribution can't place particles inside the world.
+pub fn generate(
+    number: usize,
+    world_size: Vector2,
+    mass: f32,
+    mass_deviation: f32,
+    diameter: f32,
+    options: &GenerationOptions,
+) -> Result<Vec<Particle>, SpatialDistributionError> {
+    let mut rng = rand::thread_rng();
+    let mass_distribution = options
+        .mass_distribution
+        .unwrap_or_else(|| MassDistribution::uniform_unchecked(mass, mass_deviation));
+
+    let mut particles: Vec<Particle> = options
+        .spatial_distribution
+        .sample(number, world_size, &mut rng)?
+        .into_iter()
+        .map(|position| {
+            Particle::new_rand_at(
+                position,
+                &mass_distribution,
+                options.diameter_law,
+                mass,
+                diameter,
+            )
+        })
+        .collect();
+
+    // Velocities depend on the whole distribution, so they come last
+    options.velocity_profile.apply(&mut particles);
+
+    Ok(particles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_options_match_new_rand() {
+        let particles = generate(
+            200,
+            Vector2::new(100.0, 100.0),
+            10.0,
+            50.0,
+            2.0,
+            &GenerationOptions::new(),
+        )
+        .unwrap();
+
+        assert_eq!(particles.len(), 200);
+        for p in particles {
+            assert!((5.0..=15.0).contains(&p.mass));
+            assert!((p.diameter - 2.0 * p.mass / 10.0).abs() < 1e-4);
+            assert!((0.0..=100.0).contains(&p.position.x));
+        }
+    }
+
+    #[test]
+    fn test_mass_distribution_and_diameter_law() {
+        let options = GenerationOptions::new()
+            .with_mass_distribution(MassDistribution::salpeter(1.0, 50.0).unwrap())
+            .with_diameter_law(DiameterLaw::Area);
+        let particles = generate(200, Vector2::new(100.0, 100.0), 1.0, 0.0, 2.0, &options).unwrap();
+
+        for p in particles {
+            assert!((1.0..=50.0).contains(&p.mass));
+            assert!((p.diameter - 2.0 * p.mass.sqrt()).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_spatial_distribution() {
+        let options =
+            GenerationOptions::new().with_spatial_distribution(SpatialDistribution::disc(10.0));
+        let particles = generate(100, Vector2::new(100.0, 100.0), 1.0, 0.0, 1.0, &options).unwrap();
+
+        let center = Vector2::new(50.0, 50.0);
+        for p in particles {
+            assert!(p.position.distance(&center) <= 10.0 + 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_velocity_profile_applied_after_positions() {
+        let options = GenerationOptions::new().with_velocity_profile(VelocityProfile::hubble(1.0));
+        let particles = generate(100, Vector2::new(100.0, 100.0), 1.0, 0.0, 1.0, &options).unwrap();
+
+        let momentum = particles.iter().fold(Vector2::new(0.0, 0.0), |acc, p| {
+            acc + p.velocity.scale(p.mass)
+        });
+        assert!(momentum.magnitude() < 1e-2);
+        assert!(particles.iter().any(|p| p.velocity.magnitude() > 0.0));
+    }
+}
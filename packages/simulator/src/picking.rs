@@ -0,0 +1,111 @@
+use serde::Serialize;
+
+use crate::particle::Particle;
+use crate::simulation::Simulation;
+use crate::vector2::Vector2;
+
+/// A particle found under the cursor, with its index into the SoA arrays.
+#[derive(Debug, Copy, Clone, Serialize)]
+pub struct PickedParticle {
+    pub index: usize,
+    pub distance: f32,
+    #[serde(flatten)]
+    pub particle: Particle,
+}
+
+impl Simulation {
+    /// The particle closest to `point`, if it lies within `max_radius`.
+    /// Picks between steps share the cached `tree`.
+    pub fn pick_particle(&self, point: Vector2, max_radius: f32) -> Option<PickedParticle> {
+        let index = *self.nearest_particles(point, 1).first()?;
+        let distance = point.distance(&Vector2::new(
+            self.positions_x[index],
+            self.positions_y[index],
+        ));
+
+        if distance > max_radius {
+            return None;
+        }
+
+        Some(PickedParticle {
+            index,
+            distance,
+            particle: self.particle(index),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pick_nearest_within_radius() {
+        let particles: Vec<Particle> = [(10.0, 10.0), (20.0, 10.0), (60.0, 60.0)]
+            .iter()
+            .map(|&(x, y)| {
+                Particle::new(
+                    x,
+                    2.0,
+                    Vector2::new(x, y),
+                    Vector2::new(1.0, -1.0),
+                    [10.0, 20.0, 30.0],
+                )
+            })
+            .collect();
+        let ids: Vec<i32> = particles.iter().map(|p| p.id).collect();
+        let sim = Simulation::new(particles);
+
+        let picked = sim.pick_particle(Vector2::new(18.0, 11.0), 5.0).unwrap();
+
+        assert_eq!(picked.index, 1);
+        assert_eq!(picked.particle.id, ids[1]);
+        assert_eq!(picked.particle.mass, 20.0);
+        assert_eq!(picked.particle.velocity.y, -1.0);
+        assert_eq!(picked.particle.color_b, 30.0);
+        assert_eq!(sim.index_of(ids[1]), Some(1));
+
+        assert!(sim.pick_particle(Vector2::new(40.0, 40.0), 5.0).is_none());
+    }
+
+    #[test]
+    fn test_pick_sees_moved_particles() {
+        let particles: Vec<Particle> = [(10.0, 10.0), (60.0, 60.0)]
+            .iter()
+            .map(|&(x, y)| {
+                Particle::new(
+                    1.0,
+                    1.0,
+                    Vector2::new(x, y),
+                    Vector2::new(0.0, 0.0),
+                    [0.0; 3],
+                )
+            })
+            .collect();
+        let mut sim = Simulation::new(particles);
+        assert_eq!(
+            sim.pick_particle(Vector2::new(10.0, 10.0), 1.0)
+                .unwrap()
+                .index,
+            0
+        );
+
+        sim.set_position(0, Vector2::new(40.0, 40.0)).unwrap();
+        assert!(sim.pick_particle(Vector2::new(10.0, 10.0), 1.0).is_none());
+        assert_eq!(
+            sim.pick_particle(Vector2::new(40.0, 40.0), 1.0)
+                .unwrap()
+                .index,
+            0
+        );
+
+        sim.velocities_x[1] = 10.0;
+        sim.step(Vector2::new(100.0, 100.0), 0.0, 1.0, 1.0);
+        assert_eq!(
+            sim.pick_particle(Vector2::new(70.0, 60.0), 1.0)
+                .unwrap()
+                .index,
+            1
+        );
+    }
+}
@@ -0,0 +1,25 @@
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::vector2::Vector2;
+
+/// Line of sight used to flatten 3D snapshots: the named axis is dropped
+/// and the other two, in order, become the simulation's x and y.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum ProjectionAxis {
+    X,
+    Y,
+    #[default]
+    Z,
+}
+
+impl ProjectionAxis {
+    pub fn project(&self, v: [f32; 3]) -> Vector2 {
+        match self {
+            ProjectionAxis::X => Vector2::new(v[1], v[2]),
+            ProjectionAxis::Y => Vector2::new(v[0], v[2]),
+            ProjectionAxis::Z => Vector2::new(v[0], v[1]),
+        }
+    }
+}
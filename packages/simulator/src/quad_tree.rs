@@ -1,4 +1,9 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use crate::quadrant::Quadrant;
 use crate::rectangle::Rectangle;
+use crate::utils::calculation_utils::{softened_acceleration, softened_potential};
 use crate::utils::quadrant_utils::{find_quadrant, quadrant_to_rectangle};
 use crate::vector2::Vector2;
 
@@ -11,6 +16,35 @@ pub enum QuadNode {
 
 const MAX_DEPTH: u32 = 20;
 
+// Barnes-Hut opening angle used unless a scene picks its own
+pub const DEFAULT_THETA: f32 = 0.5;
+
+const QUADRANTS: [Quadrant; 4] = [Quadrant::NW, Quadrant::NE, Quadrant::SW, Quadrant::SE];
+
+/// A particle index and its squared distance to a query point, ordered by
+/// distance so the farthest candidate sits on top of a `BinaryHeap`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Neighbor {
+    distance_sq: f32,
+    index: usize,
+}
+
+impl Eq for Neighbor {}
+
+impl Ord for Neighbor {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance_sq
+            .total_cmp(&other.distance_sq)
+            .then(self.index.cmp(&other.index))
+    }
+}
+
+impl PartialOrd for Neighbor {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 #[derive(Debug)]
 pub struct QuadTree {
     pub boundary: Rectangle,
@@ -96,12 +130,14 @@ impl QuadTree {
         self.total_mass = total_mass_new;
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn compute_force(
         &self,
         p_pos: Vector2,
         gravity: f32,
         epsilon: f32,
         scale: f32,
+        theta: f32,
         pos_x: &[f32],
         pos_y: &[f32],
         masses: &[f32],
@@ -113,15 +149,16 @@ impl QuadTree {
         let s = self.boundary.width;
         let d = self.center_of_mass.distance(&p_pos);
 
-        // Barnes-Hut threshold
-        let theta = 0.5;
-
         // If the node is far enough, use its center of mass as a single particle
         if d > 0.0 && s / d < theta {
-            // We need a dummy particle for softened_gravitational_force
-            // but softened_gravitational_force takes Particle refs.
-            // Let's refactor softened_gravitational_force to take components.
-            return self.force_from_mass(p_pos, self.center_of_mass, self.total_mass, gravity, epsilon, scale);
+            return softened_acceleration(
+                p_pos,
+                self.center_of_mass,
+                self.total_mass,
+                gravity,
+                epsilon,
+                scale,
+            );
         }
 
         let mut acceleration = Vector2::new(0.0, 0.0);
@@ -132,28 +169,231 @@ impl QuadTree {
                     for &idx in indices {
                         let other_pos = Vector2::new(pos_x[idx], pos_y[idx]);
                         let other_mass = masses[idx];
-                        acceleration = acceleration + self.force_from_mass(p_pos, other_pos, other_mass, gravity, epsilon, scale);
+                        acceleration = acceleration
+                            + softened_acceleration(
+                                p_pos, other_pos, other_mass, gravity, epsilon, scale,
+                            );
                     }
                 }
                 QuadNode::Internal(quad_tree) => {
-                    acceleration = acceleration + quad_tree.compute_force(p_pos, gravity, epsilon, scale, pos_x, pos_y, masses);
+                    acceleration = acceleration
+                        + quad_tree.compute_force(
+                            p_pos, gravity, epsilon, scale, theta, pos_x, pos_y, masses,
+                        );
                 }
             }
         }
         acceleration
     }
-    
-    fn force_from_mass(&self, p_pos: Vector2, other_pos: Vector2, other_mass: f32, gravity: f32, epsilon: f32, scale: f32) -> Vector2 {
-        if p_pos.x == other_pos.x && p_pos.y == other_pos.y {
-            return Vector2::new(0.0, 0.0);
+
+    /// Softened gravitational potential at `p_pos`, `-G Σ m / sqrt(r^2 + eps^2)`,
+    /// approximated with the same opening criterion as `compute_force`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn compute_potential(
+        &self,
+        p_pos: Vector2,
+        gravity: f32,
+        epsilon: f32,
+        theta: f32,
+        pos_x: &[f32],
+        pos_y: &[f32],
+        masses: &[f32],
+    ) -> f64 {
+        if self.total_mass == 0.0 {
+            return 0.0;
         }
 
-        let distance_vector = other_pos - p_pos; // Vector from self (p2) to other (p1)
-        let r_sq = distance_vector.x.powi(2) + distance_vector.y.powi(2);
-        let force_magnitude_scaled = gravity * other_mass / (r_sq + epsilon.powi(2)).powf(1.5);
-        
-        // Acceleration = (G * m1 * r_vec) / (r^2 + eps^2)^1.5 * scale
-        distance_vector.scale(force_magnitude_scaled * scale)
+        let s = self.boundary.width;
+        let d = self.center_of_mass.distance(&p_pos);
+
+        if d > 0.0 && s / d < theta {
+            return softened_potential(
+                p_pos,
+                self.center_of_mass,
+                self.total_mass,
+                gravity,
+                epsilon,
+            );
+        }
+
+        let mut potential = 0.0;
+        for quad_node in &self.children {
+            match quad_node {
+                QuadNode::Empty => {}
+                QuadNode::Leaf(indices) => {
+                    for &idx in indices {
+                        let other_pos = Vector2::new(pos_x[idx], pos_y[idx]);
+                        potential +=
+                            softened_potential(p_pos, other_pos, masses[idx], gravity, epsilon);
+                    }
+                }
+                QuadNode::Internal(quad_tree) => {
+                    potential += quad_tree
+                        .compute_potential(p_pos, gravity, epsilon, theta, pos_x, pos_y, masses);
+                }
+            }
+        }
+        potential
+    }
+
+    /// Indices of the `k` particles closest to `point`, nearest first.
+    /// `exclude` skips one index, typically the particle being queried.
+    pub fn nearest_neighbors(
+        &self,
+        point: Vector2,
+        k: usize,
+        exclude: Option<usize>,
+        pos_x: &[f32],
+        pos_y: &[f32],
+    ) -> Vec<usize> {
+        let mut heap = BinaryHeap::with_capacity(k + 1);
+        if k > 0 {
+            self.collect_nearest(point, k, exclude, pos_x, pos_y, &mut heap);
+        }
+
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|neighbor| neighbor.index)
+            .collect()
+    }
+
+    fn collect_nearest(
+        &self,
+        point: Vector2,
+        k: usize,
+        exclude: Option<usize>,
+        pos_x: &[f32],
+        pos_y: &[f32],
+        heap: &mut BinaryHeap<Neighbor>,
+    ) {
+        // Visit the closest quadrants first so the heap tightens early
+        let mut children: Vec<(f32, &QuadNode)> = self
+            .children
+            .iter()
+            .zip(QUADRANTS.iter())
+            .filter(|(quad_node, _)| !matches!(quad_node, QuadNode::Empty))
+            .map(|(quad_node, quadrant)| {
+                let boundary = quadrant_to_rectangle(&self.boundary, quadrant);
+                (boundary.distance_sq_to(&point), quad_node)
+            })
+            .collect();
+        children.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        for (distance_sq, quad_node) in children {
+            if heap.len() == k && heap.peek().is_some_and(|far| distance_sq > far.distance_sq) {
+                continue;
+            }
+
+            match quad_node {
+                QuadNode::Empty => {}
+                QuadNode::Leaf(indices) => {
+                    for &idx in indices {
+                        if exclude == Some(idx) {
+                            continue;
+                        }
+
+                        let dx = pos_x[idx] - point.x;
+                        let dy = pos_y[idx] - point.y;
+                        heap.push(Neighbor {
+                            distance_sq: dx * dx + dy * dy,
+                            index: idx,
+                        });
+                        if heap.len() > k {
+                            heap.pop();
+                        }
+                    }
+                }
+                QuadNode::Internal(quad_tree) => {
+                    quad_tree.collect_nearest(point, k, exclude, pos_x, pos_y, heap);
+                }
+            }
+        }
+    }
+
+    /// Indices of every particle within `radius` of `point`, in tree order.
+    pub fn within_radius(
+        &self,
+        point: Vector2,
+        radius: f32,
+        pos_x: &[f32],
+        pos_y: &[f32],
+    ) -> Vec<usize> {
+        let mut found = Vec::new();
+        self.collect_within_radius(point, radius * radius, pos_x, pos_y, &mut found);
+        found
+    }
+
+    fn collect_within_radius(
+        &self,
+        point: Vector2,
+        radius_sq: f32,
+        pos_x: &[f32],
+        pos_y: &[f32],
+        found: &mut Vec<usize>,
+    ) {
+        for (quad_node, quadrant) in self.children.iter().zip(QUADRANTS.iter()) {
+            if matches!(quad_node, QuadNode::Empty) {
+                continue;
+            }
+            let boundary = quadrant_to_rectangle(&self.boundary, quadrant);
+            if boundary.distance_sq_to(&point) > radius_sq {
+                continue;
+            }
+
+            match quad_node {
+                QuadNode::Empty => {}
+                QuadNode::Leaf(indices) => {
+                    found.extend(indices.iter().copied().filter(|&idx| {
+                        let dx = pos_x[idx] - point.x;
+                        let dy = pos_y[idx] - point.y;
+                        dx * dx + dy * dy <= radius_sq
+                    }));
+                }
+                QuadNode::Internal(quad_tree) => {
+                    quad_tree.collect_within_radius(point, radius_sq, pos_x, pos_y, found);
+                }
+            }
+        }
+    }
+
+    /// Indices of every particle inside `rectangle`, in tree order.
+    pub fn within_rectangle(
+        &self,
+        rectangle: &Rectangle,
+        pos_x: &[f32],
+        pos_y: &[f32],
+    ) -> Vec<usize> {
+        let mut found = Vec::new();
+        self.collect_within_rectangle(rectangle, pos_x, pos_y, &mut found);
+        found
+    }
+
+    fn collect_within_rectangle(
+        &self,
+        rectangle: &Rectangle,
+        pos_x: &[f32],
+        pos_y: &[f32],
+        found: &mut Vec<usize>,
+    ) {
+        for (quad_node, quadrant) in self.children.iter().zip(QUADRANTS.iter()) {
+            if !quadrant_to_rectangle(&self.boundary, quadrant).intersects(rectangle) {
+                continue;
+            }
+
+            match quad_node {
+                QuadNode::Empty => {}
+                QuadNode::Leaf(indices) => {
+                    found.extend(
+                        indices.iter().copied().filter(|&idx| {
+                            rectangle.contains(&Vector2::new(pos_x[idx], pos_y[idx]))
+                        }),
+                    );
+                }
+                QuadNode::Internal(quad_tree) => {
+                    quad_tree.collect_within_rectangle(rectangle, pos_x, pos_y, found);
+                }
+            }
+        }
     }
 }
 
@@ -188,6 +428,91 @@ mod tests {
         assert_eq!(quadtree.center_of_mass, Vector2::new(50.0, 50.0));
     }
 
+    #[test]
+    fn test_compute_potential_matches_direct_sum() {
+        let mut quadtree = QuadTree::new(Rectangle::new(Vector2::new(0.0, 0.0), 100.0, 100.0));
+
+        let pos_x = vec![10.0, 90.0, 10.0, 90.0];
+        let pos_y = vec![10.0, 10.0, 90.0, 90.0];
+        let masses = vec![1.0, 2.0, 3.0, 4.0];
+
+        for i in 0..4 {
+            quadtree.insert(i, &pos_x, &pos_y, &masses);
+        }
+
+        let potential = quadtree.compute_potential(
+            Vector2::new(10.0, 10.0),
+            1.0,
+            0.0,
+            DEFAULT_THETA,
+            &pos_x,
+            &pos_y,
+            &masses,
+        );
+        let expected = -(2.0 / 80.0 + 3.0 / 80.0 + 4.0 / (2.0_f64 * 80.0 * 80.0).sqrt());
+
+        assert!((potential - expected).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_nearest_neighbors() {
+        let mut quadtree = QuadTree::new(Rectangle::new(Vector2::new(0.0, 0.0), 100.0, 100.0));
+
+        let pos_x = vec![10.0, 12.0, 90.0, 50.0, 11.0];
+        let pos_y = vec![10.0, 10.0, 90.0, 50.0, 14.0];
+        let masses = vec![1.0; 5];
+
+        for i in 0..5 {
+            quadtree.insert(i, &pos_x, &pos_y, &masses);
+        }
+
+        let nearest =
+            quadtree.nearest_neighbors(Vector2::new(10.0, 10.0), 3, Some(0), &pos_x, &pos_y);
+        assert_eq!(nearest, vec![1, 4, 3]);
+
+        let all = quadtree.nearest_neighbors(Vector2::new(100.0, 100.0), 10, None, &pos_x, &pos_y);
+        assert_eq!(all, vec![2, 3, 4, 1, 0]);
+    }
+
+    #[test]
+    fn test_within_radius() {
+        let mut quadtree = QuadTree::new(Rectangle::new(Vector2::new(0.0, 0.0), 100.0, 100.0));
+
+        let pos_x = vec![10.0, 12.0, 90.0, 50.0, 11.0];
+        let pos_y = vec![10.0, 10.0, 90.0, 50.0, 14.0];
+        let masses = vec![1.0; 5];
+
+        for i in 0..5 {
+            quadtree.insert(i, &pos_x, &pos_y, &masses);
+        }
+
+        let mut near = quadtree.within_radius(Vector2::new(10.0, 10.0), 4.5, &pos_x, &pos_y);
+        near.sort();
+        assert_eq!(near, vec![0, 1, 4]);
+
+        assert!(quadtree
+            .within_radius(Vector2::new(70.0, 70.0), 20.0, &pos_x, &pos_y)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_within_rectangle() {
+        let mut quadtree = QuadTree::new(Rectangle::new(Vector2::new(0.0, 0.0), 100.0, 100.0));
+
+        let pos_x = vec![10.0, 12.0, 90.0, 50.0, 11.0];
+        let pos_y = vec![10.0, 10.0, 90.0, 50.0, 14.0];
+        let masses = vec![1.0; 5];
+
+        for i in 0..5 {
+            quadtree.insert(i, &pos_x, &pos_y, &masses);
+        }
+
+        let area = Rectangle::new(Vector2::new(11.0, 5.0), 40.0, 45.0);
+        let mut inside = quadtree.within_rectangle(&area, &pos_x, &pos_y);
+        inside.sort();
+        assert_eq!(inside, vec![1, 3, 4]);
+    }
+
     #[test]
     fn test_identical_positions_stack_overflow() {
         let mut quadtree = QuadTree::new(Rectangle::new(Vector2::new(0.0, 0.0), 100.0, 100.0));
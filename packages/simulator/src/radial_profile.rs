@@ -0,0 +1,281 @@
+use std::f64::consts::PI;
+
+use serde::Serialize;
+
+use crate::simulation::Simulation;
+
+/// Mass fractions reported as Lagrangian radii when the caller picks none.
+pub const DEFAULT_LAGRANGIAN_FRACTIONS: [f32; 3] = [0.1, 0.5, 0.9];
+
+/// Annuli around the center of mass, one entry per bin in every field.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RadialProfile {
+    pub inner_radii: Vec<f32>,
+    pub outer_radii: Vec<f32>,
+    pub counts: Vec<usize>,
+    /// Mass per unit area of the annulus
+    pub density: Vec<f32>,
+    /// Mass inside the outer edge of the bin, including particles inside
+    /// the innermost edge
+    pub enclosed_mass: Vec<f32>,
+    /// `sqrt(G M(<r) / r)` at the outer edge of the bin
+    pub circular_velocity: Vec<f32>,
+    /// Mass-weighted RMS deviation of the velocity from the bin's mean,
+    /// both components together
+    pub velocity_dispersion: Vec<f32>,
+}
+
+impl Simulation {
+    /// Distances from the center of mass and velocities relative to it.
+    fn center_of_mass_frame(&self) -> Vec<(f64, f64, f64)> {
+        let (center, center_velocity) = self.center_of_mass();
+
+        (0..self.count)
+            .map(|i| {
+                let dx = (self.positions_x[i] - center.x) as f64;
+                let dy = (self.positions_y[i] - center.y) as f64;
+                let vx = (self.velocities_x[i] - center_velocity.x) as f64;
+                let vy = (self.velocities_y[i] - center_velocity.y) as f64;
+                ((dx * dx + dy * dy).sqrt(), vx, vy)
+            })
+            .collect()
+    }
+
+    /// Profiles over `bins` annuli between `inner_radius` and `outer_radius`.
+    /// A non-positive `outer_radius` reaches the farthest particle. With
+    /// `logarithmic` spacing a non-positive `inner_radius` is replaced by a
+    /// thousandth of the outer one.
+    pub fn radial_profile(
+        &self,
+        gravity: f32,
+        bins: usize,
+        inner_radius: f32,
+        outer_radius: f32,
+        logarithmic: bool,
+    ) -> RadialProfile {
+        let frame = self.center_of_mass_frame();
+        if bins == 0 {
+            return RadialProfile::default();
+        }
+
+        let outer = if outer_radius > 0.0 {
+            outer_radius as f64
+        } else {
+            frame.iter().map(|&(r, _, _)| r).fold(0.0, f64::max)
+        };
+        let inner = if logarithmic && inner_radius <= 0.0 {
+            outer * 1e-3
+        } else {
+            (inner_radius.max(0.0) as f64).min(outer)
+        };
+
+        let edges: Vec<f64> = (0..=bins)
+            .map(|k| {
+                let t = k as f64 / bins as f64;
+                if logarithmic && inner > 0.0 {
+                    inner * (outer / inner).powf(t)
+                } else {
+                    inner + (outer - inner) * t
+                }
+            })
+            .collect();
+
+        let mut counts = vec![0; bins];
+        let mut mass = vec![0.0; bins];
+        let mut momentum = vec![(0.0, 0.0); bins];
+        let mut speed_sq = vec![0.0; bins];
+        let mut mass_inside = 0.0;
+
+        for (i, &(r, vx, vy)) in frame.iter().enumerate() {
+            let m = self.masses[i] as f64;
+            if r < inner {
+                mass_inside += m;
+                continue;
+            }
+            // Upper edges are inclusive so the farthest particle is counted
+            let bin = match edges[1..].iter().position(|&edge| r <= edge) {
+                Some(bin) => bin,
+                None => continue,
+            };
+
+            counts[bin] += 1;
+            mass[bin] += m;
+            momentum[bin].0 += m * vx;
+            momentum[bin].1 += m * vy;
+            speed_sq[bin] += m * (vx * vx + vy * vy);
+        }
+
+        let mut profile = RadialProfile::default();
+        let mut enclosed = mass_inside;
+
+        for bin in 0..bins {
+            let (r_in, r_out) = (edges[bin], edges[bin + 1]);
+            enclosed += mass[bin];
+
+            let area = PI * (r_out * r_out - r_in * r_in);
+            let dispersion = if mass[bin] > 0.0 {
+                let mean_vx = momentum[bin].0 / mass[bin];
+                let mean_vy = momentum[bin].1 / mass[bin];
+                let variance = speed_sq[bin] / mass[bin] - (mean_vx * mean_vx + mean_vy * mean_vy);
+                variance.max(0.0).sqrt()
+            } else {
+                0.0
+            };
+
+            profile.inner_radii.push(r_in as f32);
+            profile.outer_radii.push(r_out as f32);
+            profile.counts.push(counts[bin]);
+            profile
+                .density
+                .push(if area > 0.0 { mass[bin] / area } else { 0.0 } as f32);
+            profile.enclosed_mass.push(enclosed as f32);
+            profile.circular_velocity.push(if r_out > 0.0 {
+                (gravity as f64 * enclosed / r_out).sqrt() as f32
+            } else {
+                0.0
+            });
+            profile.velocity_dispersion.push(dispersion as f32);
+        }
+
+        profile
+    }
+
+    /// Radii around the center of mass enclosing each fraction of the total
+    /// mass, in the order the fractions were given.
+    pub fn lagrangian_radii(&self, fractions: &[f32]) -> Vec<f32> {
+        let frame = self.center_of_mass_frame();
+        let mut shells: Vec<(f64, f64)> = frame
+            .iter()
+            .enumerate()
+            .map(|(i, &(r, _, _))| (r, self.masses[i] as f64))
+            .collect();
+        shells.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let total_mass: f64 = shells.iter().map(|&(_, m)| m).sum();
+        // Fractions like 0.1 widen from f32 to slightly more than k/N, which
+        // would otherwise count one particle too many. The slack covers that
+        // rounding but stays under half a particle so it never skips one.
+        let smallest_mass = shells
+            .iter()
+            .map(|&(_, m)| m)
+            .filter(|&m| m > 0.0)
+            .fold(f64::INFINITY, f64::min);
+        let slack = (f32::EPSILON as f64 * total_mass).min(0.5 * smallest_mass);
+
+        fractions
+            .iter()
+            .map(|&fraction| {
+                let target = fraction.clamp(0.0, 1.0) as f64 * total_mass - slack;
+                let mut enclosed = 0.0;
+                shells
+                    .iter()
+                    .find(|&&(_, m)| {
+                        enclosed += m;
+                        enclosed >= target
+                    })
+                    .or(shells.last())
+                    .map_or(0.0, |&(r, _)| r as f32)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::particle::Particle;
+    use crate::vector2::Vector2;
+
+    fn body(position: Vector2, velocity: Vector2) -> Particle {
+        Particle::new(1.0, 1.0, position, velocity, [255.0, 255.0, 255.0])
+    }
+
+    /// Ten particles on each of the circles r = 0.5, 1.5, ..., 9.5 around
+    /// the origin, so that no ring sits on a bin edge.
+    fn concentric_rings() -> Simulation {
+        let particles = (1..=10)
+            .flat_map(|ring| {
+                (0..10).map(move |k| {
+                    let angle = k as f32 * std::f32::consts::TAU / 10.0;
+                    let direction = Vector2::new(angle.cos(), angle.sin());
+                    body(
+                        direction.scale(ring as f32 - 0.5),
+                        Vector2::new(-direction.y, direction.x),
+                    )
+                })
+            })
+            .collect();
+        Simulation::new(particles)
+    }
+
+    #[test]
+    fn test_radial_profile_bins() {
+        let sim = concentric_rings();
+
+        let profile = sim.radial_profile(1.0, 5, 0.0, 10.0, false);
+
+        assert_eq!(profile.counts, vec![20; 5]);
+        assert_eq!(profile.outer_radii[4], 10.0);
+        assert!((profile.enclosed_mass[2] - 60.0).abs() < 1e-4);
+        assert!((profile.enclosed_mass[4] - 100.0).abs() < 1e-4);
+        assert!((profile.circular_velocity[4] - 10.0_f32.sqrt()).abs() < 1e-4);
+        // Mass 20 over the annulus between r = 0 and r = 2
+        let expected = 20.0 / (std::f32::consts::PI * 4.0);
+        assert!((profile.density[0] - expected).abs() < 1e-4);
+        // Unit speeds pointing all around the circle average out to zero
+        assert!((profile.velocity_dispersion[3] - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_logarithmic_bins_reach_farthest_particle() {
+        let sim = concentric_rings();
+
+        let profile = sim.radial_profile(1.0, 4, 0.095, 0.0, true);
+
+        assert!((profile.outer_radii[3] - 9.5).abs() < 1e-4);
+        assert!((profile.outer_radii[1] - 0.95).abs() < 1e-4);
+        assert_eq!(profile.counts.iter().sum::<usize>(), 100);
+    }
+
+    #[test]
+    fn test_lagrangian_radii() {
+        let sim = concentric_rings();
+
+        // Ten equal masses per ring: 10% of the mass ends on the first ring
+        let radii = sim.lagrangian_radii(&DEFAULT_LAGRANGIAN_FRACTIONS);
+        assert_eq!(radii.len(), 3);
+        assert!((radii[0] - 0.5).abs() < 1e-4);
+        assert!((radii[1] - 4.5).abs() < 1e-4);
+        assert!((radii[2] - 8.5).abs() < 1e-4);
+
+        let radii = sim.lagrangian_radii(&[0.25, 0.5, 0.75, 1.0]);
+
+        assert!((radii[0] - 2.5).abs() < 1e-4);
+        assert!((radii[1] - 4.5).abs() < 1e-4);
+        assert!((radii[2] - 7.5).abs() < 1e-4);
+        assert!((radii[3] - 9.5).abs() < 1e-4);
+
+        // Just past a ring needs the next one
+        let radii = sim.lagrangian_radii(&[0.11, 0.55]);
+
+        assert!((radii[0] - 1.5).abs() < 1e-4);
+        assert!((radii[1] - 5.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_lagrangian_radii_of_many_particles() {
+        // Pairs at x = ±k, so the k-th pair brings the count to 2k
+        let n = 2_000_000;
+        let particles = (1..=n / 2)
+            .flat_map(|k| {
+                [-1.0, 1.0]
+                    .map(|side| body(Vector2::new(side * k as f32, 0.0), Vector2::new(0.0, 0.0)))
+            })
+            .collect();
+        let sim = Simulation::new(particles);
+
+        let radii = sim.lagrangian_radii(&[0.1, 0.3, 0.5, 0.9]);
+
+        assert_eq!(radii, vec![100_000.0, 300_000.0, 500_000.0, 900_000.0]);
+    }
+}
@@ -15,4 +15,32 @@ impl Rectangle {
             height,
         }
     }
+
+    /// Squared distance from `point` to the closest point of the rectangle,
+    /// zero when the point lies inside.
+    pub fn distance_sq_to(&self, point: &Vector2) -> f32 {
+        let dx = (self.position.x - point.x)
+            .max(point.x - (self.position.x + self.width))
+            .max(0.0);
+        let dy = (self.position.y - point.y)
+            .max(point.y - (self.position.y + self.height))
+            .max(0.0);
+        dx * dx + dy * dy
+    }
+
+    /// Whether `point` lies inside, edges included.
+    pub fn contains(&self, point: &Vector2) -> bool {
+        point.x >= self.position.x
+            && point.x <= self.position.x + self.width
+            && point.y >= self.position.y
+            && point.y <= self.position.y + self.height
+    }
+
+    /// Whether the two rectangles overlap, touching edges included.
+    pub fn intersects(&self, other: &Rectangle) -> bool {
+        self.position.x <= other.position.x + other.width
+            && other.position.x <= self.position.x + self.width
+            && self.position.y <= other.position.y + other.height
+            && other.position.y <= self.position.y + self.height
+    }
 }
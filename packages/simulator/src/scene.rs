@@ -0,0 +1,290 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::particle::Particle;
+use crate::simulation::{Simulation, SimulationParams};
+use crate::vector2::Vector2;
+
+/// Bumped whenever the schema changes incompatibly.
+pub const SCENE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct SceneMetadata {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub author: String,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScenePhysics {
+    pub world_width: f32,
+    pub world_height: f32,
+    pub gravity: f32,
+    pub epsilon: f32,
+    pub time_step: f32,
+    pub theta: f32,
+}
+
+/// A shareable plain-text scene: what it is, how it is simulated, and the
+/// particles in it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Scene {
+    pub version: u32,
+    #[serde(default)]
+    pub metadata: SceneMetadata,
+    pub physics: ScenePhysics,
+    #[serde(default)]
+    pub time: f64,
+    #[serde(default)]
+    pub step_count: u64,
+    pub particles: Vec<Particle>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SceneError {
+    /// Not valid JSON, or not shaped like a scene
+    Parse(String),
+    UnsupportedVersion(u32),
+    InvalidPhysics(&'static str),
+    InvalidParticle {
+        index: usize,
+        reason: &'static str,
+    },
+    DuplicateId(i32),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::Parse(message) => write!(f, "Malformed scene: {}", message),
+            SceneError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported scene version {}", version)
+            }
+            SceneError::InvalidPhysics(reason) => write!(f, "Invalid physics: {}", reason),
+            SceneError::InvalidParticle { index, reason } => {
+                write!(f, "Invalid particle {}: {}", index, reason)
+            }
+            SceneError::DuplicateId(id) => write!(f, "Duplicate particle ID {}", id),
+        }
+    }
+}
+
+impl Scene {
+    /// Parses a scene without checking its contents, which
+    /// `Simulation::from_scene` does before building from it.
+    pub fn from_json(json: &str) -> Result<Scene, SceneError> {
+        serde_json::from_str(json).map_err(|e| SceneError::Parse(e.to_string()))
+    }
+
+    /// Refuses scenes that would not load again, such as ones with NaN
+    /// values, which JSON can only write as `null`.
+    pub fn to_json(&self) -> Result<String, SceneError> {
+        self.validate()?;
+        // Plain structs of numbers and strings always serialize
+        Ok(serde_json::to_string_pretty(self).unwrap())
+    }
+
+    pub fn validate(&self) -> Result<(), SceneError> {
+        if self.version != SCENE_VERSION {
+            return Err(SceneError::UnsupportedVersion(self.version));
+        }
+
+        let physics = &self.physics;
+        // Zero until the first step, as for any simulation not yet run
+        if ![physics.world_width, physics.world_height]
+            .iter()
+            .all(|&side| side >= 0.0 && side.is_finite())
+        {
+            return Err(SceneError::InvalidPhysics(
+                "world size must be non-negative",
+            ));
+        }
+        if !physics.gravity.is_finite() {
+            return Err(SceneError::InvalidPhysics("gravity must be finite"));
+        }
+        if !(physics.epsilon >= 0.0 && physics.epsilon.is_finite()) {
+            return Err(SceneError::InvalidPhysics("softening must be non-negative"));
+        }
+        if !(physics.time_step >= 0.0 && physics.time_step.is_finite()) {
+            return Err(SceneError::InvalidPhysics("time step must be non-negative"));
+        }
+        if !(physics.theta >= 0.0 && physics.theta.is_finite()) {
+            return Err(SceneError::InvalidPhysics("theta must be non-negative"));
+        }
+
+        let mut ids = HashSet::with_capacity(self.particles.len());
+        for (index, p) in self.particles.iter().enumerate() {
+            p.validate()
+                .map_err(|reason| SceneError::InvalidParticle { index, reason })?;
+            if !ids.insert(p.id) {
+                return Err(SceneError::DuplicateId(p.id));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Simulation {
+    pub fn to_scene(&self, metadata: SceneMetadata) -> Scene {
+        Scene {
+            version: SCENE_VERSION,
+            metadata,
+            physics: ScenePhysics {
+                world_width: self.params.world_size.x,
+                world_height: self.params.world_size.y,
+                gravity: self.params.gravity,
+                epsilon: self.params.epsilon,
+                time_step: self.params.time_step,
+                theta: self.theta,
+            },
+            time: self.time,
+            step_count: self.step_count,
+            particles: (0..self.count).map(|i| self.particle(i)).collect(),
+        }
+    }
+
+    /// Builds a simulation from a scene, which is validated first.
+    pub fn from_scene(scene: Scene) -> Result<Simulation, SceneError> {
+        scene.validate()?;
+
+        let mut sim = Simulation::new(scene.particles);
+        sim.theta = scene.physics.theta;
+        sim.params = SimulationParams {
+            world_size: Vector2::new(scene.physics.world_width, scene.physics.world_height),
+            gravity: scene.physics.gravity,
+            epsilon: scene.physics.epsilon,
+            time_step: scene.physics.time_step,
+        };
+        sim.time = scene.time;
+        sim.step_count = scene.step_count;
+        Ok(sim)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::particle_generator::{self, GenerationOptions};
+
+    fn load(json: &str) -> Result<Simulation, SceneError> {
+        Scene::from_json(json).and_then(Simulation::from_scene)
+    }
+
+    fn scene_json(particles: &str) -> String {
+        format!(
+            r#"{{
+                "version": 1,
+                "metadata": {{ "name": "pair" }},
+                "physics": {{
+                    "world_width": 100, "world_height": 100, "gravity": 1,
+                    "epsilon": 0.5, "time_step": 0.01, "theta": 0.5
+                }},
+                "particles": [{}]
+            }}"#,
+            particles
+        )
+    }
+
+    fn particle_json(id: i32, mass: f32) -> String {
+        format!(
+            r#"{{ "id": {}, "mass": {}, "diameter": 1, "position": {{ "x": 1, "y": 2 }},
+                "velocity": {{ "x": 0, "y": 0 }}, "color_r": 255, "color_g": 0, "color_b": 0 }}"#,
+            id, mass
+        )
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut sim = load(&scene_json(&particle_json(7, 2.0))).unwrap();
+        sim.step(Vector2::new(100.0, 100.0), 1.0, 0.5, 0.01);
+
+        let metadata = SceneMetadata {
+            name: "pair".to_string(),
+            ..SceneMetadata::default()
+        };
+        let json = sim.to_scene(metadata.clone()).to_json().unwrap();
+        let scene = Scene::from_json(&json).unwrap();
+
+        assert_eq!(scene.metadata, metadata);
+        assert_eq!(scene.step_count, 1);
+        assert_eq!(scene.physics.world_width, 100.0);
+        assert_eq!(scene.particles[0].id, 7);
+        assert_eq!(scene.particles[0].mass, 2.0);
+    }
+
+    #[test]
+    fn test_validation_errors() {
+        assert!(matches!(
+            load("{ \"version\": 1 "),
+            Err(SceneError::Parse(_))
+        ));
+        assert_eq!(
+            load(&scene_json(&particle_json(1, -1.0))).unwrap_err(),
+            SceneError::InvalidParticle {
+                index: 0,
+                reason: "mass must be non-negative"
+            }
+        );
+
+        let duplicates = format!("{}, {}", particle_json(3, 1.0), particle_json(3, 1.0));
+        assert_eq!(
+            load(&scene_json(&duplicates)).unwrap_err(),
+            SceneError::DuplicateId(3)
+        );
+
+        let future = scene_json("").replace("\"version\": 1", "\"version\": 2");
+        assert_eq!(
+            load(&future).unwrap_err(),
+            SceneError::UnsupportedVersion(2)
+        );
+
+        let endless = scene_json("").replace("\"world_width\": 100", "\"world_width\": 1e39");
+        assert_eq!(
+            load(&endless).unwrap_err(),
+            SceneError::InvalidPhysics("world size must be non-negative")
+        );
+    }
+
+    #[test]
+    fn test_non_finite_values_are_not_exported() {
+        let mut sim = load(&scene_json(&particle_json(1, 1.0))).unwrap();
+        sim.velocities_x[0] = f32::NAN;
+
+        assert_eq!(
+            sim.to_scene(SceneMetadata::default())
+                .to_json()
+                .unwrap_err(),
+            SceneError::InvalidParticle {
+                index: 0,
+                reason: "position and velocity must be finite"
+            }
+        );
+    }
+
+    #[test]
+    fn test_large_generated_scene_round_trips() {
+        let world_size = Vector2::new(1000.0, 1000.0);
+        let particles = particle_generator::generate(
+            100_000,
+            world_size,
+            1.0,
+            10.0,
+            1.0,
+            &GenerationOptions::new(),
+        )
+        .unwrap();
+        let mut sim = Simulation::new(particles);
+        sim.params.world_size = world_size;
+
+        let json = sim.to_scene(SceneMetadata::default()).to_json().unwrap();
+        let restored = load(&json).unwrap();
+
+        assert_eq!(restored.ids, sim.ids);
+        assert_eq!(restored.positions_x, sim.positions_x);
+    }
+}
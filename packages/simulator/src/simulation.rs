@@ -1,10 +1,42 @@
+use std::cell::OnceCell;
+use std::collections::hash_map::{Entry, HashMap};
+
+use crate::diagnostics::PotentialMethod;
+use crate::drift_tracker::DriftTracker;
 use crate::particle::Particle;
-use crate::quad_tree::QuadTree;
+use crate::quad_tree::{QuadTree, DEFAULT_THETA};
 use crate::rectangle::Rectangle;
 use crate::vector2::Vector2;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+/// Physical parameters of the most recent `step`, kept so a saved scene can
+/// resume with the same settings.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimulationParams {
+    pub world_size: Vector2,
+    pub gravity: f32,
+    pub epsilon: f32,
+    pub time_step: f32,
+}
+
+impl Default for SimulationParams {
+    fn default() -> Self {
+        SimulationParams {
+            world_size: Vector2::new(0.0, 0.0),
+            gravity: 0.0,
+            epsilon: 0.0,
+            time_step: 0.0,
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct Simulation {
+    /// Unique per particle; replace through `set_ids` so `index_of` stays in
+    /// step
+    pub(crate) ids: Vec<i32>,
     pub positions_x: Vec<f32>,
     pub positions_y: Vec<f32>,
     pub velocities_x: Vec<f32>,
@@ -12,12 +44,55 @@ pub struct Simulation {
     pub masses: Vec<f32>,
     pub diameters: Vec<f32>,
     pub colors: Vec<f32>, // Flat [r, g, b, r, g, b, ...]
+    /// Filled by `estimate_densities`, zero until then
+    pub densities: Vec<f32>,
     pub count: usize,
+    pub theta: f32,
+    pub params: SimulationParams,
+    /// Kept in f64 so long runs keep advancing by whole time steps
+    pub time: f64,
+    pub step_count: u64,
+    pub drift: Option<DriftTracker>,
+    /// Source of every random choice made during a run, saved in checkpoints
+    pub rng: ChaCha8Rng,
+    id_index: HashMap<i32, usize>,
+    /// Built by `tree` on first use. Cleared by `step` and the setters that
+    /// move or reweigh particles; call `invalidate_tree` after writing the
+    /// position or mass arrays directly.
+    tree: OnceCell<QuadTree>,
+}
+
+/// Maps every ID to its index. Particles whose ID is already taken get the
+/// lowest free one, so a lookup by ID can never land on the wrong particle.
+fn index_ids(ids: &mut [i32]) -> HashMap<i32, usize> {
+    let mut index = HashMap::with_capacity(ids.len());
+    let mut duplicates = Vec::new();
+    for (i, &id) in ids.iter().enumerate() {
+        match index.entry(id) {
+            Entry::Occupied(_) => duplicates.push(i),
+            Entry::Vacant(entry) => {
+                entry.insert(i);
+            }
+        }
+    }
+
+    let mut next_id = 0;
+    for i in duplicates {
+        while index.contains_key(&next_id) {
+            next_id += 1;
+        }
+        ids[i] = next_id;
+        index.insert(next_id, i);
+    }
+    index
 }
 
 impl Simulation {
+    /// Particles keep their IDs unless one is already taken, in which case
+    /// they get the lowest free ID.
     pub fn new(particles: Vec<Particle>) -> Simulation {
         let count = particles.len();
+        let mut ids = Vec::with_capacity(count);
         let mut positions_x = Vec::with_capacity(count);
         let mut positions_y = Vec::with_capacity(count);
         let mut velocities_x = Vec::with_capacity(count);
@@ -27,6 +102,7 @@ impl Simulation {
         let mut colors = Vec::with_capacity(count * 3);
 
         for p in particles {
+            ids.push(p.id);
             positions_x.push(p.position.x);
             positions_y.push(p.position.y);
             velocities_x.push(p.velocity.x);
@@ -37,8 +113,10 @@ impl Simulation {
             colors.push(p.color_g);
             colors.push(p.color_b);
         }
+        let id_index = index_ids(&mut ids);
 
         Simulation {
+            ids,
             positions_x,
             positions_y,
             velocities_x,
@@ -46,11 +124,145 @@ impl Simulation {
             masses,
             diameters,
             colors,
+            densities: vec![0.0; count],
             count,
+            theta: DEFAULT_THETA,
+            params: SimulationParams::default(),
+            time: 0.0,
+            step_count: 0,
+            drift: None,
+            rng: ChaCha8Rng::from_entropy(),
+            id_index,
+            tree: OnceCell::new(),
+        }
+    }
+
+    /// Restarts the random sequence from `seed`, for reproducible runs.
+    pub fn seed(&mut self, seed: u64) {
+        self.rng = ChaCha8Rng::seed_from_u64(seed);
+    }
+
+    pub fn ids(&self) -> &[i32] {
+        &self.ids
+    }
+
+    /// Replaces every particle's ID, renumbering duplicates as `new` does.
+    pub fn set_ids(&mut self, ids: Vec<i32>) {
+        self.ids = ids;
+        self.id_index = index_ids(&mut self.ids);
+        self.invalidate_tree();
+    }
+
+    /// Index of the particle with the given ID, if any.
+    pub fn index_of(&self, id: i32) -> Option<usize> {
+        self.id_index.get(&id).copied()
+    }
+
+    /// The particle at `index`, reassembled from the SoA arrays.
+    pub fn particle(&self, index: usize) -> Particle {
+        Particle {
+            id: self.ids[index],
+            mass: self.masses[index],
+            diameter: self.diameters[index],
+            position: Vector2::new(self.positions_x[index], self.positions_y[index]),
+            velocity: Vector2::new(self.velocities_x[index], self.velocities_y[index]),
+            color_r: self.colors[index * 3],
+            color_g: self.colors[index * 3 + 1],
+            color_b: self.colors[index * 3 + 2],
+        }
+    }
+
+    /// Smallest square containing every particle, used for trees built
+    /// outside of `step` where the world size isn't known.
+    pub fn bounding_rectangle(&self) -> Rectangle {
+        let mut min = Vector2::new(f32::INFINITY, f32::INFINITY);
+        let mut max = Vector2::new(f32::NEG_INFINITY, f32::NEG_INFINITY);
+
+        for i in 0..self.count {
+            min.x = min.x.min(self.positions_x[i]);
+            min.y = min.y.min(self.positions_y[i]);
+            max.x = max.x.max(self.positions_x[i]);
+            max.y = max.y.max(self.positions_y[i]);
+        }
+
+        if self.count == 0 {
+            return Rectangle::new(Vector2::new(0.0, 0.0), 1.0, 1.0);
+        }
+
+        // Pad slightly so rounding never pushes the farthest particle outside
+        let side = ((max.x - min.x).max(max.y - min.y) * 1.0001).max(1.0);
+        Rectangle::new(min, side, side)
+    }
+
+    pub fn build_tree(&self) -> QuadTree {
+        let mut q = QuadTree::new(self.bounding_rectangle());
+
+        for i in 0..self.count {
+            q.insert(i, &self.positions_x, &self.positions_y, &self.masses);
+        }
+
+        q
+    }
+
+    /// Tree over the current positions, shared by the queries below and kept
+    /// until the particles move.
+    pub fn tree(&self) -> &QuadTree {
+        self.tree.get_or_init(|| self.build_tree())
+    }
+
+    pub fn invalidate_tree(&mut self) {
+        self.tree.take();
+    }
+
+    /// Indices of the `k` particles closest to `point`, nearest first.
+    pub fn nearest_particles(&self, point: Vector2, k: usize) -> Vec<usize> {
+        self.tree()
+            .nearest_neighbors(point, k, None, &self.positions_x, &self.positions_y)
+    }
+
+    pub fn particles_within_radius(&self, point: Vector2, radius: f32) -> Vec<usize> {
+        self.tree()
+            .within_radius(point, radius, &self.positions_x, &self.positions_y)
+    }
+
+    pub fn particles_within_rectangle(&self, rectangle: &Rectangle) -> Vec<usize> {
+        self.tree()
+            .within_rectangle(rectangle, &self.positions_x, &self.positions_y)
+    }
+
+    /// Starts recording conservation drift every `interval` steps into a ring
+    /// buffer of `capacity` samples, relative to the state at the next step.
+    pub fn track_drift(&mut self, interval: u64, capacity: usize, method: PotentialMethod) {
+        self.drift = Some(DriftTracker::new(interval, capacity, method));
+    }
+
+    fn sample_drift(&mut self) {
+        let method = match &self.drift {
+            Some(tracker) if !tracker.has_baseline() || tracker.is_due(self.step_count) => {
+                tracker.method
+            }
+            _ => return,
+        };
+
+        let diagnostics = self.diagnostics(method);
+        if let Some(tracker) = self.drift.as_mut() {
+            tracker.record(self.time, &diagnostics);
         }
     }
 
     pub fn step(&mut self, world_size: Vector2, gravity: f32, epsilon: f32, time_step: f32) {
+        self.params = SimulationParams {
+            world_size,
+            gravity,
+            epsilon,
+            time_step,
+        };
+
+        // First sample after tracking starts is the baseline
+        if matches!(&self.drift, Some(tracker) if !tracker.has_baseline()) {
+            self.sample_drift();
+        }
+
         let boundary = Rectangle::new(Vector2::new(0.0, 0.0), world_size.x, world_size.y);
         let mut q = QuadTree::new(boundary);
         
@@ -65,6 +277,7 @@ impl Simulation {
                 gravity,
                 epsilon,
                 time_step,
+                self.theta,
                 &self.positions_x,
                 &self.positions_y,
                 &self.masses,
@@ -83,6 +296,25 @@ impl Simulation {
                  self.velocities_y[i] = 0.0;
             }
         }
+
+        self.time += time_step as f64;
+        self.step_count += 1;
+        self.invalidate_tree();
+        self.sample_drift();
+    }
+
+    /// Takes `steps` steps with the physics in `params`, as recorded by the
+    /// last `step` or set by `SimulationBuilder`.
+    pub fn run(&mut self, steps: usize) {
+        let params = self.params;
+        for _ in 0..steps {
+            self.step(
+                params.world_size,
+                params.gravity,
+                params.epsilon,
+                params.time_step,
+            );
+        }
     }
 }
 
@@ -119,5 +351,83 @@ mod tests {
         // Particle 2 should have moved towards Particle 1 (negative x direction)
         assert!(sim.positions_x[1] < 10.0);
         assert_eq!(sim.positions_y[1], 0.0);
+        assert_eq!(sim.time, 1.0);
+        assert_eq!(sim.step_count, 1);
+    }
+
+    #[test]
+    fn test_time_advances_in_long_runs() {
+        let mut sim = Simulation::new(Vec::new());
+        sim.time = 1.0e7;
+
+        sim.step(Vector2::new(100.0, 100.0), 1.0, 0.0, 1.0e-3);
+
+        assert!((sim.time - (1.0e7 + 1.0e-3)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_duplicate_ids_are_renumbered() {
+        let particles: Vec<Particle> = [5, 0, 5, 1, 0]
+            .iter()
+            .map(|&id| {
+                let mut p = Particle::new(
+                    1.0,
+                    1.0,
+                    Vector2::new(id as f32, 0.0),
+                    Vector2::new(0.0, 0.0),
+                    [255.0, 255.0, 255.0],
+                );
+                p.id = id;
+                p
+            })
+            .collect();
+
+        let mut sim = Simulation::new(particles);
+
+        assert_eq!(sim.ids(), &[5, 0, 2, 1, 3]);
+        for (index, &id) in sim.ids().iter().enumerate() {
+            assert_eq!(sim.index_of(id), Some(index));
+        }
+        assert_eq!(sim.index_of(4), None);
+
+        sim.set_ids(vec![7, 7, 8, 9, 0]);
+        assert_eq!(sim.ids(), &[7, 1, 8, 9, 0]);
+        assert_eq!(sim.index_of(1), Some(1));
+    }
+
+    #[test]
+    fn test_drift_tracking() {
+        // Circular two-body orbit, bodies at r = 1 from the center of mass
+        let speed = 0.5;
+        let p1 = Particle::new(
+            1.0,
+            0.1,
+            Vector2::new(49.0, 50.0),
+            Vector2::new(0.0, -speed),
+            [255.0, 255.0, 255.0],
+        );
+        let p2 = Particle::new(
+            1.0,
+            0.1,
+            Vector2::new(51.0, 50.0),
+            Vector2::new(0.0, speed),
+            [255.0, 255.0, 255.0],
+        );
+
+        let mut sim = Simulation::new(vec![p1, p2]);
+        sim.track_drift(10, 8, PotentialMethod::Direct);
+
+        for _ in 0..100 {
+            sim.step(Vector2::new(100.0, 100.0), 1.0, 0.0, 1.0e-3);
+        }
+
+        let tracker = sim.drift.as_ref().unwrap();
+        let samples = tracker.samples();
+        assert_eq!(samples.len(), 8);
+        assert!((samples[7].0 - 0.1).abs() < 1e-5);
+        for (_, energy_error, angular_momentum_error) in samples {
+            assert!(energy_error.abs() < 1e-3);
+            assert!(angular_momentum_error.abs() < 1e-3);
+        }
     }
 }
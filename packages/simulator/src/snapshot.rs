@@ -0,0 +1,229 @@
+use std::fmt;
+
+use crate::simulation::{Simulation, SimulationParams};
+use crate::utils::binary_utils::{ByteReader, ByteWriter};
+use crate::utils::checksum::crc32;
+use crate::vector2::Vector2;
+
+const MAGIC: &[u8; 8] = b"NBODYSIM";
+
+/// Bumped whenever the layout below changes.
+pub const SNAPSHOT_VERSION: u32 = 2;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SnapshotError {
+    BadMagic,
+    UnsupportedVersion(u32),
+    ChecksumMismatch,
+    Truncated,
+    TrailingBytes,
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnapshotError::BadMagic => write!(f, "Not a simulation snapshot"),
+            SnapshotError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported snapshot version {}", version)
+            }
+            SnapshotError::ChecksumMismatch => write!(f, "Snapshot checksum mismatch"),
+            SnapshotError::Truncated => write!(f, "Snapshot is truncated"),
+            SnapshotError::TrailingBytes => write!(f, "Unexpected data after snapshot"),
+        }
+    }
+}
+
+/// Binary snapshot, all values little-endian:
+///
+/// ```text
+/// magic "NBODYSIM", version u32, count u64, time f64, step_count u64,
+/// theta f32, world width f32, world height f32, gravity f32, epsilon f32,
+/// time_step f32, ids i32 * count, positions_x, positions_y, velocities_x,
+/// velocities_y, masses, diameters f32 * count, colors f32 * 3 * count,
+/// densities f32 * count, CRC-32 u32 of everything before it
+/// ```
+///
+/// Drift tracking is not saved and starts switched off after loading.
+impl Simulation {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = ByteWriter::new();
+
+        writer.write_bytes(MAGIC);
+        writer.write_u32(SNAPSHOT_VERSION);
+        writer.write_u64(self.count as u64);
+        writer.write_f64(self.time);
+        writer.write_u64(self.step_count);
+        writer.write_f32(self.theta);
+        writer.write_f32(self.params.world_size.x);
+        writer.write_f32(self.params.world_size.y);
+        writer.write_f32(self.params.gravity);
+        writer.write_f32(self.params.epsilon);
+        writer.write_f32(self.params.time_step);
+
+        writer.write_i32_slice(&self.ids);
+        for values in [
+            &self.positions_x,
+            &self.positions_y,
+            &self.velocities_x,
+            &self.velocities_y,
+            &self.masses,
+            &self.diameters,
+            &self.colors,
+            &self.densities,
+        ] {
+            writer.write_f32_slice(values);
+        }
+
+        let checksum = crc32(writer.bytes());
+        writer.write_u32(checksum);
+        writer.into_bytes()
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Simulation, SnapshotError> {
+        let mut reader = ByteReader::new(bytes);
+
+        if reader.read_bytes(MAGIC.len()) != Some(&MAGIC[..]) {
+            return Err(SnapshotError::BadMagic);
+        }
+        let version = reader.read_u32().ok_or(SnapshotError::Truncated)?;
+        if version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+
+        let body_len = bytes.len().checked_sub(4).ok_or(SnapshotError::Truncated)?;
+        let (body, checksum) = bytes.split_at(body_len);
+        if crc32(body) != u32::from_le_bytes(checksum.try_into().unwrap()) {
+            return Err(SnapshotError::ChecksumMismatch);
+        }
+
+        let mut reader = ByteReader::new(body);
+        reader.read_bytes(MAGIC.len() + 4);
+        let sim = read_simulation(&mut reader).ok_or(SnapshotError::Truncated)?;
+        if reader.remaining() > 0 {
+            return Err(SnapshotError::TrailingBytes);
+        }
+        Ok(sim)
+    }
+}
+
+/// Everything between the version and the checksum, `None` when it runs out.
+fn read_simulation(reader: &mut ByteReader) -> Option<Simulation> {
+    let count = usize::try_from(reader.read_u64()?).ok()?;
+    let time = reader.read_f64()?;
+    let step_count = reader.read_u64()?;
+    let theta = reader.read_f32()?;
+    let params = SimulationParams {
+        world_size: Vector2::new(reader.read_f32()?, reader.read_f32()?),
+        gravity: reader.read_f32()?,
+        epsilon: reader.read_f32()?,
+        time_step: reader.read_f32()?,
+    };
+
+    let mut sim = Simulation::new(Vec::new());
+    sim.set_ids(reader.read_i32_vec(count)?);
+    sim.positions_x = reader.read_f32_vec(count)?;
+    sim.positions_y = reader.read_f32_vec(count)?;
+    sim.velocities_x = reader.read_f32_vec(count)?;
+    sim.velocities_y = reader.read_f32_vec(count)?;
+    sim.masses = reader.read_f32_vec(count)?;
+    sim.diameters = reader.read_f32_vec(count)?;
+    sim.colors = reader.read_f32_vec(count.checked_mul(3)?)?;
+    sim.densities = reader.read_f32_vec(count)?;
+    sim.count = count;
+    sim.theta = theta;
+    sim.params = params;
+    sim.time = time;
+    sim.step_count = step_count;
+    Some(sim)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::particle::Particle;
+
+    fn stepped_simulation() -> Simulation {
+        let mut sim = Simulation::new(
+            (0..5)
+                .map(|i| {
+                    Particle::new(
+                        1.0 + i as f32,
+                        2.0,
+                        Vector2::new(10.0 * i as f32, 5.0 + i as f32),
+                        Vector2::new(0.5, -0.25 * i as f32),
+                        [i as f32, 100.0, 200.0],
+                    )
+                })
+                .collect(),
+        );
+        sim.theta = 0.7;
+        for _ in 0..3 {
+            sim.step(Vector2::new(100.0, 100.0), 1.0, 0.5, 0.01);
+        }
+        sim
+    }
+
+    #[test]
+    fn test_round_trip_restores_state() {
+        let sim = stepped_simulation();
+
+        let restored = Simulation::from_bytes(&sim.to_bytes()).unwrap();
+
+        assert_eq!(restored.count, 5);
+        assert_eq!(restored.ids, sim.ids);
+        assert_eq!(restored.positions_x, sim.positions_x);
+        assert_eq!(restored.velocities_y, sim.velocities_y);
+        assert_eq!(restored.masses, sim.masses);
+        assert_eq!(restored.colors, sim.colors);
+        assert_eq!(restored.time, sim.time);
+        assert_eq!(restored.step_count, 3);
+        assert_eq!(restored.theta, 0.7);
+        assert_eq!(restored.params, sim.params);
+    }
+
+    #[test]
+    fn test_restored_simulation_continues_identically() {
+        let mut sim = stepped_simulation();
+        let mut restored = Simulation::from_bytes(&sim.to_bytes()).unwrap();
+
+        for _ in 0..10 {
+            sim.step(Vector2::new(100.0, 100.0), 1.0, 0.5, 0.01);
+            restored.step(Vector2::new(100.0, 100.0), 1.0, 0.5, 0.01);
+        }
+
+        assert_eq!(restored.to_bytes(), sim.to_bytes());
+    }
+
+    #[test]
+    fn test_corrupt_snapshots_are_rejected() {
+        let bytes = stepped_simulation().to_bytes();
+
+        let mut flipped = bytes.clone();
+        flipped[40] ^= 1;
+        assert_eq!(
+            Simulation::from_bytes(&flipped).unwrap_err(),
+            SnapshotError::ChecksumMismatch
+        );
+
+        let mut versioned = bytes.clone();
+        versioned[8] = 99;
+        assert_eq!(
+            Simulation::from_bytes(&versioned).unwrap_err(),
+            SnapshotError::UnsupportedVersion(99)
+        );
+
+        assert_eq!(
+            Simulation::from_bytes(b"not a snapshot").unwrap_err(),
+            SnapshotError::BadMagic
+        );
+
+        // Valid checksum over a body that ends early
+        let mut short = bytes[..bytes.len() - 12].to_vec();
+        let checksum = crc32(&short);
+        short.extend_from_slice(&checksum.to_le_bytes());
+        assert_eq!(
+            Simulation::from_bytes(&short).unwrap_err(),
+            SnapshotError::Truncated
+        );
+    }
+}
@@ -0,0 +1,407 @@
+use std::f32::consts::{FRAC_PI_2, TAU};
+use std::fmt;
+
+use rand::prelude::*;
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::utils::random_utils::standard_normal;
+use crate::vector2::Vector2;
+
+/// Draws per point before giving up on a shape that hardly overlaps the world.
+const MAX_REDRAWS: usize = 1000;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum SpatialLaw {
+    Uniform,
+    Disc {
+        radius: f32,
+    },
+    Ring {
+        inner_radius: f32,
+        outer_radius: f32,
+    },
+    Lattice {
+        jitter: f32,
+    },
+    GaussianClumps {
+        clumps: usize,
+        sigma: f32,
+    },
+    Spiral {
+        arms: u32,
+        pitch: f32,
+        inner_radius: f32,
+        outer_radius: f32,
+        spread: f32,
+    },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SpatialDistributionError {
+    /// A spiral pitch outside (0, π/2), where the arms don't wind
+    InvalidPitch,
+    /// A shape that hardly overlaps the world, or has non-finite parameters
+    OutsideWorld,
+}
+
+impl fmt::Display for SpatialDistributionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SpatialDistributionError::InvalidPitch => {
+                write!(f, "Spiral pitch must be between 0 and π/2 radians")
+            }
+            SpatialDistributionError::OutsideWorld => {
+                write!(
+                    f,
+                    "Shape doesn't fit the world well enough to place particles"
+                )
+            }
+        }
+    }
+}
+
+/// Where particles are placed when generating a scene. Every shape except
+/// `uniform` and `lattice` is centered on the middle of the world. Points a
+/// shape puts past the world's edges are redrawn, as the tree `step` builds
+/// only covers the world.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SpatialDistribution {
+    law: SpatialLaw,
+}
+
+impl Default for SpatialDistribution {
+    fn default() -> Self {
+        SpatialDistribution::uniform()
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl SpatialDistribution {
+    /// Uniform over the whole world rectangle, as `Particle::new_rand` does.
+    pub fn uniform() -> SpatialDistribution {
+        SpatialDistribution {
+            law: SpatialLaw::Uniform,
+        }
+    }
+
+    pub fn disc(radius: f32) -> SpatialDistribution {
+        SpatialDistribution {
+            law: SpatialLaw::Disc { radius },
+        }
+    }
+
+    /// Uniform over the annulus between the two radii.
+    pub fn ring(inner_radius: f32, outer_radius: f32) -> SpatialDistribution {
+        SpatialDistribution {
+            law: SpatialLaw::Ring {
+                inner_radius,
+                outer_radius,
+            },
+        }
+    }
+
+    /// Regular grid filling the world, each point displaced by up to
+    /// `jitter` times the grid spacing.
+    pub fn lattice(jitter: f32) -> SpatialDistribution {
+        SpatialDistribution {
+            law: SpatialLaw::Lattice { jitter },
+        }
+    }
+
+    /// `clumps` Gaussian blobs of standard deviation `sigma` at random
+    /// centers, sharing the particles evenly. Clumps beyond the particle
+    /// count would stay empty and are never drawn.
+    pub fn gaussian_clumps(clumps: usize, sigma: f32) -> SpatialDistribution {
+        SpatialDistribution {
+            law: SpatialLaw::GaussianClumps { clumps, sigma },
+        }
+    }
+}
+
+impl SpatialDistribution {
+    /// Logarithmic spiral arms `r = inner_radius * e^(tan(pitch) * θ)`, with
+    /// particles scattered off the arm by `spread` radians. `pitch` must lie
+    /// strictly between 0 and π/2.
+    pub fn spiral(
+        arms: u32,
+        pitch: f32,
+        inner_radius: f32,
+        outer_radius: f32,
+        spread: f32,
+    ) -> Result<SpatialDistribution, SpatialDistributionError> {
+        if !(pitch > 0.0 && pitch < FRAC_PI_2) {
+            return Err(SpatialDistributionError::InvalidPitch);
+        }
+
+        Ok(SpatialDistribution {
+            law: SpatialLaw::Spiral {
+                arms,
+                pitch,
+                inner_radius,
+                outer_radius,
+                spread,
+            },
+        })
+    }
+
+    /// `count` points inside the world, or `OutsideWorld` when a point
+    /// still lands outside after `MAX_REDRAWS` tries.
+    pub fn sample<R: Rng + ?Sized>(
+        &self,
+        count: usize,
+        world_size: Vector2,
+        rng: &mut R,
+    ) -> Result<Vec<Vector2>, SpatialDistributionError> {
+        let center = world_size.scale(0.5);
+        match self.law {
+            SpatialLaw::Uniform => Ok((0..count)
+                .map(|_| {
+                    Vector2::new(
+                        rng.gen::<f32>() * world_size.x,
+                        rng.gen::<f32>() * world_size.y,
+                    )
+                })
+                .collect()),
+            SpatialLaw::Disc { radius } => (0..count)
+                .map(|_| {
+                    draw_inside(world_size, rng, |rng| {
+                        center + sample_annulus(0.0, radius, rng)
+                    })
+                })
+                .collect(),
+            SpatialLaw::Ring {
+                inner_radius,
+                outer_radius,
+            } => (0..count)
+                .map(|_| {
+                    draw_inside(world_size, rng, |rng| {
+                        center + sample_annulus(inner_radius, outer_radius, rng)
+                    })
+                })
+                .collect(),
+            SpatialLaw::Lattice { jitter } => {
+                if count == 0 {
+                    return Ok(Vec::new());
+                }
+
+                // Keep cells roughly square whatever the world's aspect ratio
+                let columns =
+                    ((count as f32 * world_size.x / world_size.y).sqrt().ceil() as usize).max(1);
+                let rows = count.div_ceil(columns);
+                let spacing =
+                    Vector2::new(world_size.x / columns as f32, world_size.y / rows as f32);
+
+                (0..count)
+                    .map(|i| {
+                        let cell = Vector2::new((i % columns) as f32, (i / columns) as f32);
+                        draw_inside(world_size, rng, |rng| {
+                            lattice_point(cell, spacing, jitter, rng)
+                        })
+                    })
+                    .collect()
+            }
+            SpatialLaw::GaussianClumps { clumps, sigma } => {
+                let centers: Vec<Vector2> = (0..clumps.clamp(1, count.max(1)))
+                    .map(|_| {
+                        Vector2::new(
+                            rng.gen::<f32>() * world_size.x,
+                            rng.gen::<f32>() * world_size.y,
+                        )
+                    })
+                    .collect();
+
+                (0..count)
+                    .map(|i| {
+                        let clump = centers[i % centers.len()];
+                        draw_inside(world_size, rng, |rng| {
+                            let offset = Vector2::new(standard_normal(rng), standard_normal(rng));
+                            clump + offset.scale(sigma)
+                        })
+                    })
+                    .collect()
+            }
+            SpatialLaw::Spiral {
+                arms,
+                pitch,
+                inner_radius,
+                outer_radius,
+                spread,
+            } => {
+                let arms = arms.max(1);
+                let tightness = pitch.tan();
+
+                (0..count)
+                    .map(|i| {
+                        let arm_offset = (i as u32 % arms) as f32 / arms as f32 * TAU;
+                        draw_inside(world_size, rng, |rng| {
+                            let r = sample_annulus(inner_radius, outer_radius, rng).magnitude();
+                            let theta = (r / inner_radius.max(f32::MIN_POSITIVE)).ln() / tightness
+                                + arm_offset
+                                + standard_normal(rng) * spread;
+                            center + Vector2::new(r * theta.cos(), r * theta.sin())
+                        })
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// The first point from `draw` inside the world, giving up after
+/// `MAX_REDRAWS` tries.
+fn draw_inside<R: Rng + ?Sized>(
+    world_size: Vector2,
+    rng: &mut R,
+    mut draw: impl FnMut(&mut R) -> Vector2,
+) -> Result<Vector2, SpatialDistributionError> {
+    (0..MAX_REDRAWS)
+        .map(|_| draw(rng))
+        .find(|p| (0.0..=world_size.x).contains(&p.x) && (0.0..=world_size.y).contains(&p.y))
+        .ok_or(SpatialDistributionError::OutsideWorld)
+}
+
+/// Uniform point in the annulus, drawn with an area-weighted radius.
+fn sample_annulus<R: Rng + ?Sized>(inner_radius: f32, outer_radius: f32, rng: &mut R) -> Vector2 {
+    let inner_sq = inner_radius * inner_radius;
+    let outer_sq = outer_radius * outer_radius;
+    let r = (inner_sq + rng.gen::<f32>() * (outer_sq - inner_sq)).sqrt();
+    let theta = rng.gen::<f32>() * TAU;
+    Vector2::new(r * theta.cos(), r * theta.sin())
+}
+
+/// Center of the lattice `cell`, displaced by up to `jitter` cells.
+fn lattice_point<R: Rng + ?Sized>(
+    cell: Vector2,
+    spacing: Vector2,
+    jitter: f32,
+    rng: &mut R,
+) -> Vector2 {
+    Vector2::new(
+        (cell.x + 0.5 + jitter * (rng.gen::<f32>() - 0.5)) * spacing.x,
+        (cell.y + 0.5 + jitter * (rng.gen::<f32>() - 0.5)) * spacing.y,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WORLD: Vector2 = Vector2 { x: 200.0, y: 100.0 };
+
+    fn sample(distribution: SpatialDistribution, count: usize) -> Vec<Vector2> {
+        let mut rng = StdRng::seed_from_u64(3);
+        distribution.sample(count, WORLD, &mut rng).unwrap()
+    }
+
+    #[test]
+    fn test_disc_and_ring_radii() {
+        let center = WORLD.scale(0.5);
+
+        for p in sample(SpatialDistribution::disc(30.0), 500) {
+            assert!(p.distance(&center) <= 30.0 + 1e-3);
+        }
+        for p in sample(SpatialDistribution::ring(20.0, 30.0), 500) {
+            let r = p.distance(&center);
+            assert!((20.0 - 1e-3..=30.0 + 1e-3).contains(&r));
+        }
+    }
+
+    #[test]
+    fn test_lattice_without_jitter_is_regular() {
+        let points = sample(SpatialDistribution::lattice(0.0), 8);
+
+        // 8 points in a 2:1 world -> 4 columns, 2 rows
+        assert_eq!(points[0], Vector2::new(25.0, 25.0));
+        assert_eq!(points[3], Vector2::new(175.0, 25.0));
+        assert_eq!(points[4], Vector2::new(25.0, 75.0));
+    }
+
+    #[test]
+    fn test_spiral_stays_within_radii() {
+        let center = WORLD.scale(0.5);
+        let spiral = SpatialDistribution::spiral(2, 0.3, 5.0, 40.0, 0.1).unwrap();
+        let points = sample(spiral, 500);
+
+        assert_eq!(points.len(), 500);
+        for p in points {
+            let r = p.distance(&center);
+            assert!((5.0 - 1e-3..=40.0 + 1e-3).contains(&r));
+        }
+    }
+
+    #[test]
+    fn test_invalid_spiral_pitch_is_rejected() {
+        for pitch in [0.0, -0.3, FRAC_PI_2, f32::NAN] {
+            assert_eq!(
+                SpatialDistribution::spiral(2, pitch, 5.0, 40.0, 0.1),
+                Err(SpatialDistributionError::InvalidPitch)
+            );
+        }
+    }
+
+    #[test]
+    fn test_oversized_shapes_stay_in_world() {
+        let distributions = [
+            SpatialDistribution::disc(300.0),
+            SpatialDistribution::ring(80.0, 300.0),
+            SpatialDistribution::lattice(3.0),
+            SpatialDistribution::gaussian_clumps(3, 50.0),
+            SpatialDistribution::spiral(2, 0.3, 5.0, 400.0, 0.5).unwrap(),
+        ];
+
+        for distribution in distributions {
+            for p in sample(distribution, 500) {
+                assert!((0.0..=WORLD.x).contains(&p.x) && (0.0..=WORLD.y).contains(&p.y));
+                // Redrawn rather than clamped onto the edges
+                assert!(p.x > 0.0 && p.x < WORLD.x && p.y > 0.0 && p.y < WORLD.y);
+            }
+        }
+    }
+
+    #[test]
+    fn test_shapes_outside_the_world_are_rejected() {
+        let mut rng = StdRng::seed_from_u64(3);
+        for distribution in [
+            SpatialDistribution::ring(500.0, 600.0),
+            SpatialDistribution::disc(f32::NAN),
+        ] {
+            assert_eq!(
+                distribution.sample(10, WORLD, &mut rng),
+                Err(SpatialDistributionError::OutsideWorld)
+            );
+        }
+    }
+
+    #[test]
+    fn test_clumps_are_bounded_by_the_particle_count() {
+        let points = sample(SpatialDistribution::gaussian_clumps(usize::MAX, 1.0), 10);
+
+        assert_eq!(points.len(), 10);
+    }
+
+    #[test]
+    fn test_gaussian_clump_mean_and_spread() {
+        // The clump center is the first point drawn from the same seed
+        let mut rng = StdRng::seed_from_u64(3);
+        let center = Vector2::new(rng.gen::<f32>() * WORLD.x, rng.gen::<f32>() * WORLD.y);
+        let points = sample(SpatialDistribution::gaussian_clumps(1, 2.0), 4000);
+
+        assert_eq!(points.len(), 4000);
+        let n = points.len() as f32;
+        let mean = points
+            .iter()
+            .fold(Vector2::new(0.0, 0.0), |sum, &p| sum + p)
+            .scale(1.0 / n);
+        let variance = points
+            .iter()
+            .map(|p| p.distance(&mean).powi(2))
+            .sum::<f32>()
+            / n;
+        // Two dimensions, each with a variance of sigma²
+        let sigma = (variance / 2.0).sqrt();
+        assert!(mean.distance(&center) < 0.2);
+        assert!((1.9..2.1).contains(&sigma));
+    }
+}
@@ -0,0 +1,455 @@
+use std::fmt;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::particle::Particle;
+use crate::projection::ProjectionAxis;
+use crate::simulation::Simulation;
+use crate::utils::binary_utils::{ByteReader, ByteWriter, Endian};
+
+/// Time, five counts and the padding that rounds the header up to 32 bytes.
+const HEADER_SIZE: usize = 32;
+
+const GAS_SIZE: usize = 12 * 4;
+const DARK_SIZE: usize = 9 * 4;
+const STAR_SIZE: usize = 11 * 4;
+
+/// One color per section: gas, dark matter, stars.
+const GAS_COLOR: [f32; 3] = [80.0, 160.0, 255.0];
+const DARK_COLOR: [f32; 3] = [200.0, 200.0, 200.0];
+const STAR_COLOR: [f32; 3] = [255.0, 255.0, 255.0];
+
+/// Byte order of a TIPSY file. Standard files are XDR, which is big-endian;
+/// native files are whatever the writing machine used.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum TipsyFormat {
+    #[default]
+    Standard,
+    Native,
+}
+
+impl TipsyFormat {
+    fn endian(&self) -> Endian {
+        match self {
+            TipsyFormat::Standard => Endian::Big,
+            TipsyFormat::Native => Endian::native(),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct TipsyGas {
+    pub mass: f32,
+    pub position: [f32; 3],
+    pub velocity: [f32; 3],
+    pub density: f32,
+    pub temperature: f32,
+    pub smoothing_length: f32,
+    pub metals: f32,
+    pub potential: f32,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct TipsyDark {
+    pub mass: f32,
+    pub position: [f32; 3],
+    pub velocity: [f32; 3],
+    pub softening: f32,
+    pub potential: f32,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct TipsyStar {
+    pub mass: f32,
+    pub position: [f32; 3],
+    pub velocity: [f32; 3],
+    pub metals: f32,
+    pub formation_time: f32,
+    pub softening: f32,
+    pub potential: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TipsySnapshot {
+    pub time: f64,
+    /// 2 or 3
+    pub ndim: i32,
+    pub gas: Vec<TipsyGas>,
+    pub dark: Vec<TipsyDark>,
+    pub star: Vec<TipsyStar>,
+}
+
+impl Default for TipsySnapshot {
+    fn default() -> Self {
+        TipsySnapshot {
+            time: 0.0,
+            ndim: 3,
+            gas: Vec::new(),
+            dark: Vec::new(),
+            star: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TipsyError {
+    Truncated,
+    /// Counts that disagree or a dimension other than 2 or 3, in either
+    /// byte order
+    BadHeader,
+    /// More particles than the header's 32-bit counts can hold
+    TooManyParticles,
+    /// A dimension other than 2 or 3 on export
+    InvalidDimension,
+}
+
+impl fmt::Display for TipsyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TipsyError::Truncated => write!(f, "TIPSY file is truncated"),
+            TipsyError::BadHeader => write!(f, "Not a TIPSY file"),
+            TipsyError::TooManyParticles => write!(f, "Too many particles for a TIPSY file"),
+            TipsyError::InvalidDimension => write!(f, "TIPSY dimension must be 2 or 3"),
+        }
+    }
+}
+
+/// Section sizes from the header, when it makes sense in this byte order.
+fn read_counts(reader: &mut ByteReader) -> Option<(i32, [usize; 3])> {
+    let nbodies = reader.read_i32()?;
+    let ndim = reader.read_i32()?;
+    let counts = [0; 3].map(|_| reader.read_i32().and_then(|n| usize::try_from(n).ok()));
+    let [Some(nsph), Some(ndark), Some(nstar)] = counts else {
+        return None;
+    };
+
+    let total = nsph.checked_add(ndark)?.checked_add(nstar)?;
+    if !(2..=3).contains(&ndim) || usize::try_from(nbodies).ok()? != total {
+        return None;
+    }
+    Some((ndim, [nsph, ndark, nstar]))
+}
+
+fn read_vector(reader: &mut ByteReader) -> Option<[f32; 3]> {
+    Some([reader.read_f32()?, reader.read_f32()?, reader.read_f32()?])
+}
+
+impl TipsySnapshot {
+    /// Reads a standard or native file, telling them apart by which byte
+    /// order gives a consistent header. Anything after the star section is
+    /// ignored.
+    pub fn from_bytes(bytes: &[u8]) -> Result<TipsySnapshot, TipsyError> {
+        let header = bytes.get(..HEADER_SIZE).ok_or(TipsyError::Truncated)?;
+        let (endian, ndim, [nsph, ndark, nstar]) = [Endian::Big, Endian::Little]
+            .into_iter()
+            .find_map(|endian| {
+                let mut reader = ByteReader::with_endian(&header[8..], endian);
+                read_counts(&mut reader).map(|(ndim, counts)| (endian, ndim, counts))
+            })
+            .ok_or(TipsyError::BadHeader)?;
+
+        let body_len = [(nsph, GAS_SIZE), (ndark, DARK_SIZE), (nstar, STAR_SIZE)]
+            .iter()
+            .try_fold(HEADER_SIZE, |len, &(n, size)| {
+                len.checked_add(n.checked_mul(size)?)
+            })
+            .ok_or(TipsyError::Truncated)?;
+        if bytes.len() < body_len {
+            return Err(TipsyError::Truncated);
+        }
+
+        let mut reader = ByteReader::with_endian(bytes, endian);
+        let time = reader.read_f64().ok_or(TipsyError::Truncated)?;
+        reader.read_bytes(HEADER_SIZE - 8);
+
+        let gas = (0..nsph)
+            .map(|_| {
+                let gas = TipsyGas {
+                    mass: reader.read_f32()?,
+                    position: read_vector(&mut reader)?,
+                    velocity: read_vector(&mut reader)?,
+                    density: reader.read_f32()?,
+                    temperature: reader.read_f32()?,
+                    smoothing_length: reader.read_f32()?,
+                    metals: reader.read_f32()?,
+                    potential: reader.read_f32()?,
+                };
+                Some(gas)
+            })
+            .collect::<Option<_>>()
+            .ok_or(TipsyError::Truncated)?;
+        let dark = (0..ndark)
+            .map(|_| {
+                let dark = TipsyDark {
+                    mass: reader.read_f32()?,
+                    position: read_vector(&mut reader)?,
+                    velocity: read_vector(&mut reader)?,
+                    softening: reader.read_f32()?,
+                    potential: reader.read_f32()?,
+                };
+                Some(dark)
+            })
+            .collect::<Option<_>>()
+            .ok_or(TipsyError::Truncated)?;
+        let star = (0..nstar)
+            .map(|_| {
+                let star = TipsyStar {
+                    mass: reader.read_f32()?,
+                    position: read_vector(&mut reader)?,
+                    velocity: read_vector(&mut reader)?,
+                    metals: reader.read_f32()?,
+                    formation_time: reader.read_f32()?,
+                    softening: reader.read_f32()?,
+                    potential: reader.read_f32()?,
+                };
+                Some(star)
+            })
+            .collect::<Option<_>>()
+            .ok_or(TipsyError::Truncated)?;
+
+        Ok(TipsySnapshot {
+            time,
+            ndim,
+            gas,
+            dark,
+            star,
+        })
+    }
+
+    pub fn to_bytes(&self, format: TipsyFormat) -> Result<Vec<u8>, TipsyError> {
+        if !(2..=3).contains(&self.ndim) {
+            return Err(TipsyError::InvalidDimension);
+        }
+        let count = |n: usize| i32::try_from(n).map_err(|_| TipsyError::TooManyParticles);
+        let counts = [
+            count(self.gas.len())?,
+            count(self.dark.len())?,
+            count(self.star.len())?,
+        ];
+        let total = counts
+            .iter()
+            .try_fold(0i32, |total, &n| total.checked_add(n))
+            .ok_or(TipsyError::TooManyParticles)?;
+
+        let mut writer = ByteWriter::with_endian(format.endian());
+        writer.write_f64(self.time);
+        writer.write_i32(total);
+        writer.write_i32(self.ndim);
+        for n in counts {
+            writer.write_i32(n);
+        }
+        writer.write_zeros(4);
+
+        for gas in &self.gas {
+            writer.write_f32(gas.mass);
+            writer.write_f32_slice(&gas.position);
+            writer.write_f32_slice(&gas.velocity);
+            writer.write_f32_slice(&[
+                gas.density,
+                gas.temperature,
+                gas.smoothing_length,
+                gas.metals,
+                gas.potential,
+            ]);
+        }
+        for dark in &self.dark {
+            writer.write_f32(dark.mass);
+            writer.write_f32_slice(&dark.position);
+            writer.write_f32_slice(&dark.velocity);
+            writer.write_f32_slice(&[dark.softening, dark.potential]);
+        }
+        for star in &self.star {
+            writer.write_f32(star.mass);
+            writer.write_f32_slice(&star.position);
+            writer.write_f32_slice(&star.velocity);
+            writer.write_f32_slice(&[
+                star.metals,
+                star.formation_time,
+                star.softening,
+                star.potential,
+            ]);
+        }
+
+        Ok(writer.into_bytes())
+    }
+}
+
+impl Simulation {
+    /// Flattens a snapshot along `axis`, coloring particles by section. TIPSY
+    /// has no particle IDs, so particles are numbered in file order.
+    pub fn from_tipsy(snapshot: &TipsySnapshot, axis: ProjectionAxis, diameter: f32) -> Simulation {
+        let gas = snapshot
+            .gas
+            .iter()
+            .map(|p| (p.mass, p.position, p.velocity, GAS_COLOR));
+        let dark = snapshot
+            .dark
+            .iter()
+            .map(|p| (p.mass, p.position, p.velocity, DARK_COLOR));
+        let star = snapshot
+            .star
+            .iter()
+            .map(|p| (p.mass, p.position, p.velocity, STAR_COLOR));
+
+        let particles = gas
+            .chain(dark)
+            .chain(star)
+            .enumerate()
+            .map(|(i, (mass, position, velocity, color))| {
+                let mut particle = Particle::new(
+                    mass,
+                    diameter,
+                    axis.project(position),
+                    axis.project(velocity),
+                    color,
+                );
+                particle.id = i as i32;
+                particle
+            })
+            .collect();
+
+        let mut sim = Simulation::new(particles);
+        sim.time = snapshot.time;
+        sim
+    }
+
+    /// All particles as dark matter with z = 0, softened by the simulation's
+    /// epsilon.
+    pub fn to_tipsy(&self) -> TipsySnapshot {
+        let dark = (0..self.count)
+            .map(|i| TipsyDark {
+                mass: self.masses[i],
+                position: [self.positions_x[i], self.positions_y[i], 0.0],
+                velocity: [self.velocities_x[i], self.velocities_y[i], 0.0],
+                softening: self.params.epsilon,
+                potential: 0.0,
+            })
+            .collect();
+
+        TipsySnapshot {
+            time: self.time,
+            ndim: 3,
+            dark,
+            ..TipsySnapshot::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot() -> TipsySnapshot {
+        TipsySnapshot {
+            time: 0.25,
+            ndim: 3,
+            gas: vec![TipsyGas {
+                mass: 0.5,
+                position: [1.0, 2.0, 3.0],
+                temperature: 1e4,
+                ..TipsyGas::default()
+            }],
+            dark: vec![TipsyDark {
+                mass: 4.0,
+                position: [4.0, 5.0, 6.0],
+                velocity: [0.1, 0.2, 0.3],
+                softening: 0.05,
+                ..TipsyDark::default()
+            }],
+            star: vec![TipsyStar {
+                mass: 2.0,
+                position: [7.0, 8.0, 9.0],
+                formation_time: 0.125,
+                ..TipsyStar::default()
+            }],
+        }
+    }
+
+    #[test]
+    fn test_round_trip_in_both_formats() {
+        for format in [TipsyFormat::Standard, TipsyFormat::Native] {
+            let bytes = snapshot().to_bytes(format).unwrap();
+
+            assert_eq!(bytes.len(), HEADER_SIZE + GAS_SIZE + DARK_SIZE + STAR_SIZE);
+            assert_eq!(TipsySnapshot::from_bytes(&bytes).unwrap(), snapshot());
+        }
+    }
+
+    #[test]
+    fn test_standard_format_is_big_endian() {
+        let bytes = snapshot().to_bytes(TipsyFormat::Standard).unwrap();
+
+        assert_eq!(&bytes[..8], &0.25f64.to_be_bytes());
+        // nbodies, ndim, nsph, ndark, nstar
+        assert_eq!(&bytes[8..12], &[0, 0, 0, 3]);
+        assert_eq!(&bytes[12..16], &[0, 0, 0, 3]);
+        assert_eq!(&bytes[HEADER_SIZE..HEADER_SIZE + 4], &0.5f32.to_be_bytes());
+    }
+
+    #[test]
+    fn test_projection_on_import() {
+        let sim = Simulation::from_tipsy(&snapshot(), ProjectionAxis::Y, 1.0);
+
+        assert_eq!(sim.ids, vec![0, 1, 2]);
+        assert_eq!(sim.positions_x, vec![1.0, 4.0, 7.0]);
+        assert_eq!(sim.positions_y, vec![3.0, 6.0, 9.0]);
+        assert_eq!(sim.velocities_y, vec![0.0, 0.3, 0.0]);
+        assert_eq!(sim.masses, vec![0.5, 4.0, 2.0]);
+        assert_eq!(sim.time, 0.25);
+        assert_eq!(&sim.colors[3..6], &DARK_COLOR);
+    }
+
+    #[test]
+    fn test_simulation_round_trip() {
+        let sim = Simulation::from_tipsy(&snapshot(), ProjectionAxis::Z, 1.0);
+
+        let bytes = sim.to_tipsy().to_bytes(TipsyFormat::Standard).unwrap();
+        let restored = TipsySnapshot::from_bytes(&bytes).unwrap();
+
+        assert_eq!(restored.dark.len(), 3);
+        assert_eq!(restored.dark[2].position, [7.0, 8.0, 0.0]);
+        assert_eq!(restored.dark[1].velocity, [0.1, 0.2, 0.0]);
+        assert_eq!(restored.dark[0].mass, 0.5);
+    }
+
+    #[test]
+    fn test_corrupt_files() {
+        let bytes = snapshot().to_bytes(TipsyFormat::Standard).unwrap();
+        assert_eq!(
+            TipsySnapshot::from_bytes(&bytes[..bytes.len() - 1]).unwrap_err(),
+            TipsyError::Truncated
+        );
+
+        let mut miscounted = bytes.clone();
+        miscounted[11] = 4;
+        assert_eq!(
+            TipsySnapshot::from_bytes(&miscounted).unwrap_err(),
+            TipsyError::BadHeader
+        );
+        assert_eq!(
+            TipsySnapshot::from_bytes(&bytes[..20]).unwrap_err(),
+            TipsyError::Truncated
+        );
+    }
+    #[test]
+    fn test_default_snapshot_round_trips() {
+        let empty = TipsySnapshot::default();
+        let bytes = empty.to_bytes(TipsyFormat::Standard).unwrap();
+
+        assert_eq!(TipsySnapshot::from_bytes(&bytes).unwrap(), empty);
+    }
+
+    #[test]
+    fn test_unwritable_snapshots() {
+        let flat = TipsySnapshot {
+            ndim: 0,
+            ..snapshot()
+        };
+        assert_eq!(
+            flat.to_bytes(TipsyFormat::Standard),
+            Err(TipsyError::InvalidDimension)
+        );
+    }
+}
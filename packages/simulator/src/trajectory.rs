@@ -0,0 +1,617 @@
+use std::fmt;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::simulation::Simulation;
+use crate::utils::binary_utils::{ByteReader, ByteWriter};
+use crate::utils::checksum::crc32;
+
+const MAGIC: &[u8; 8] = b"NBODYTRJ";
+
+/// Bumped whenever the layout written by `to_bytes` changes.
+pub const TRAJECTORY_VERSION: u32 = 2;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TrajectoryError {
+    BadMagic,
+    UnsupportedVersion(u32),
+    ChecksumMismatch,
+    Truncated,
+    /// The recorder's rounding step is zero, negative or not finite
+    InvalidPrecision,
+}
+
+impl fmt::Display for TrajectoryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TrajectoryError::BadMagic => write!(f, "Not a recorded trajectory"),
+            TrajectoryError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported trajectory version {}", version)
+            }
+            TrajectoryError::ChecksumMismatch => write!(f, "Trajectory checksum mismatch"),
+            TrajectoryError::Truncated => write!(f, "Trajectory is truncated"),
+            TrajectoryError::InvalidPrecision => {
+                write!(f, "Trajectory precision must be positive and finite")
+            }
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct FrameInfo {
+    /// Start of the frame's encoded values in `Trajectory::data`
+    offset: usize,
+    step_count: u64,
+    time: f64,
+    count: usize,
+    /// Keyframes hold IDs and absolute values; other frames hold the change
+    /// from the frame before
+    keyframe: bool,
+}
+
+/// Recorded frames: positions, and velocities when asked for, quantized to
+/// multiples of `precision` and stored as zigzag varints.
+#[derive(Debug, Clone, PartialEq)]
+struct Trajectory {
+    precision: f32,
+    velocities: bool,
+    frames: Vec<FrameInfo>,
+    data: Vec<u8>,
+}
+
+impl Trajectory {
+    fn channels(&self) -> usize {
+        if self.velocities {
+            4
+        } else {
+            2
+        }
+    }
+
+    fn quantize(&self, value: f32) -> i64 {
+        // `as` saturates, so runaway particles clamp instead of wrapping
+        (value as f64 / self.precision as f64).round() as i64
+    }
+
+    fn dequantize(&self, value: i64) -> f32 {
+        (value as f64 * self.precision as f64) as f32
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = ByteWriter::new();
+
+        writer.write_bytes(MAGIC);
+        writer.write_u32(TRAJECTORY_VERSION);
+        writer.write_f32(self.precision);
+        writer.write_bytes(&[self.velocities as u8]);
+        writer.write_u64(self.frames.len() as u64);
+        for frame in &self.frames {
+            writer.write_u64(frame.offset as u64);
+            writer.write_u64(frame.step_count);
+            writer.write_f64(frame.time);
+            writer.write_u64(frame.count as u64);
+            writer.write_bytes(&[frame.keyframe as u8]);
+        }
+        writer.write_u64(self.data.len() as u64);
+        writer.write_bytes(&self.data);
+
+        let checksum = crc32(writer.bytes());
+        writer.write_u32(checksum);
+        writer.into_bytes()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Trajectory, TrajectoryError> {
+        let mut reader = ByteReader::new(bytes);
+
+        if reader.read_bytes(MAGIC.len()) != Some(&MAGIC[..]) {
+            return Err(TrajectoryError::BadMagic);
+        }
+        let version = reader.read_u32().ok_or(TrajectoryError::Truncated)?;
+        if version != TRAJECTORY_VERSION {
+            return Err(TrajectoryError::UnsupportedVersion(version));
+        }
+
+        let body_len = bytes
+            .len()
+            .checked_sub(4)
+            .ok_or(TrajectoryError::Truncated)?;
+        let (body, checksum) = bytes.split_at(body_len);
+        if crc32(body) != u32::from_le_bytes(checksum.try_into().unwrap()) {
+            return Err(TrajectoryError::ChecksumMismatch);
+        }
+
+        let mut reader = ByteReader::new(body);
+        reader.read_bytes(MAGIC.len() + 4);
+        read_trajectory(&mut reader).ok_or(TrajectoryError::Truncated)
+    }
+}
+
+/// Everything between the version and the checksum, `None` when it runs out.
+fn read_trajectory(reader: &mut ByteReader) -> Option<Trajectory> {
+    let precision = reader.read_f32()?;
+    let velocities = reader.read_bytes(1)?[0] != 0;
+    let frame_count = usize::try_from(reader.read_u64()?).ok()?;
+
+    let mut frames = Vec::new();
+    for _ in 0..frame_count {
+        frames.push(FrameInfo {
+            offset: usize::try_from(reader.read_u64()?).ok()?,
+            step_count: reader.read_u64()?,
+            time: reader.read_f64()?,
+            count: usize::try_from(reader.read_u64()?).ok()?,
+            keyframe: reader.read_bytes(1)?[0] != 0,
+        });
+    }
+    let data_len = usize::try_from(reader.read_u64()?).ok()?;
+    let data = reader.read_bytes(data_len)?.to_vec();
+
+    // Frames must start inside the data, in order, from a keyframe
+    let offsets_valid = frames
+        .windows(2)
+        .all(|pair| pair[0].offset <= pair[1].offset)
+        && frames.last().is_none_or(|frame| frame.offset <= data.len());
+    if !offsets_valid || frames.first().is_some_and(|frame| !frame.keyframe) {
+        return None;
+    }
+
+    Some(Trajectory {
+        precision,
+        velocities,
+        frames,
+        data,
+    })
+}
+
+fn write_varint(data: &mut Vec<u8>, value: i64) {
+    let mut zigzag = ((value << 1) ^ (value >> 63)) as u64;
+    while zigzag >= 0x80 {
+        data.push(zigzag as u8 | 0x80);
+        zigzag >>= 7;
+    }
+    data.push(zigzag as u8);
+}
+
+fn read_varint(data: &[u8], offset: &mut usize) -> Option<i64> {
+    let mut zigzag = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *data.get(*offset)?;
+        *offset += 1;
+        zigzag |= ((byte & 0x7F) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Some((zigzag >> 1) as i64 ^ -((zigzag & 1) as i64));
+        }
+    }
+    None
+}
+
+/// Captures a simulation every `interval` steps. Between keyframes, which
+/// come every `keyframe_interval` frames and whenever the particles change,
+/// only the change in each quantized value is stored.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone)]
+pub struct TrajectoryRecorder {
+    trajectory: Trajectory,
+    interval: u64,
+    keyframe_interval: usize,
+    /// Quantized values of the last frame, channel after channel
+    previous: Vec<i64>,
+    /// Particles of the last keyframe, which every later delta refers to
+    ids: Vec<i32>,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl TrajectoryRecorder {
+    pub fn frames(&self) -> usize {
+        self.trajectory.frames.len()
+    }
+
+    /// Size of the encoded frames in bytes.
+    pub fn encoded_len(&self) -> usize {
+        self.trajectory.data.len()
+    }
+
+    /// Versioned, checksummed copy of everything recorded so far.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.trajectory.to_bytes()
+    }
+
+    /// A player over the frames recorded so far.
+    pub fn player(&self) -> TrajectoryPlayer {
+        TrajectoryPlayer::new(self.trajectory.clone())
+    }
+}
+
+impl TrajectoryRecorder {
+    /// `precision` is the largest rounding step for positions and
+    /// velocities; values come back within half of it.
+    pub fn new(
+        interval: u32,
+        precision: f32,
+        record_velocities: bool,
+        keyframe_interval: usize,
+    ) -> Result<TrajectoryRecorder, TrajectoryError> {
+        if !(precision > 0.0 && precision.is_finite()) {
+            return Err(TrajectoryError::InvalidPrecision);
+        }
+
+        Ok(TrajectoryRecorder {
+            trajectory: Trajectory {
+                precision,
+                velocities: record_velocities,
+                frames: Vec::new(),
+                data: Vec::new(),
+            },
+            interval: interval.max(1) as u64,
+            keyframe_interval: keyframe_interval.max(1),
+            previous: Vec::new(),
+            ids: Vec::new(),
+        })
+    }
+
+    /// Records the simulation if its step count falls on the interval and
+    /// that step isn't recorded yet. Returns whether a frame was added.
+    pub fn record(&mut self, sim: &Simulation) -> bool {
+        let frames = &self.trajectory.frames;
+        let already_recorded = frames
+            .last()
+            .is_some_and(|frame| frame.step_count == sim.step_count);
+        if !sim.step_count.is_multiple_of(self.interval) || already_recorded {
+            return false;
+        }
+
+        let keyframe = match frames.iter().rposition(|frame| frame.keyframe) {
+            Some(last_keyframe) => {
+                sim.ids != self.ids || frames.len() - last_keyframe >= self.keyframe_interval
+            }
+            None => true,
+        };
+
+        let mut channels = vec![&sim.positions_x, &sim.positions_y];
+        if self.trajectory.velocities {
+            channels.extend([&sim.velocities_x, &sim.velocities_y]);
+        }
+        let current: Vec<i64> = channels
+            .iter()
+            .flat_map(|values| values.iter().map(|&v| self.trajectory.quantize(v)))
+            .collect();
+
+        let offset = self.trajectory.data.len();
+        let data = &mut self.trajectory.data;
+        if keyframe {
+            self.ids = sim.ids.clone();
+            for &id in &sim.ids {
+                write_varint(data, id as i64);
+            }
+            for &value in &current {
+                write_varint(data, value);
+            }
+        } else {
+            for (&value, &previous) in current.iter().zip(&self.previous) {
+                write_varint(data, value.wrapping_sub(previous));
+            }
+        }
+
+        self.trajectory.frames.push(FrameInfo {
+            offset,
+            step_count: sim.step_count,
+            time: sim.time,
+            count: sim.count,
+            keyframe,
+        });
+        self.previous = current;
+        true
+    }
+}
+
+/// Random access to recorded frames. Seeking decodes forward from the
+/// nearest keyframe, or from the current frame when that is closer.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Clone)]
+pub struct TrajectoryPlayer {
+    trajectory: Trajectory,
+    frame: Option<usize>,
+    ids: Vec<i32>,
+    values: Vec<i64>,
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl TrajectoryPlayer {
+    pub fn frames(&self) -> usize {
+        self.trajectory.frames.len()
+    }
+
+    /// Moves to `frame`. Returns false, staying put, when the frame doesn't
+    /// exist or its data is corrupt.
+    pub fn seek(&mut self, frame: usize) -> bool {
+        if frame >= self.frames() {
+            return false;
+        }
+
+        let frames = &self.trajectory.frames;
+        let keyframe = frames[..=frame].iter().rposition(|f| f.keyframe).unwrap();
+        let start = match self.frame {
+            Some(current) if (keyframe..=frame).contains(&current) => current + 1,
+            _ => keyframe,
+        };
+
+        let mut ids = self.ids.clone();
+        let mut values = self.values.clone();
+        for i in start..=frame {
+            if !self.decode(i, &mut ids, &mut values) {
+                return false;
+            }
+        }
+
+        self.frame = Some(frame);
+        self.ids = ids;
+        self.values = values;
+        true
+    }
+
+    /// Index of the current frame, if any has been sought.
+    pub fn frame(&self) -> Option<usize> {
+        self.frame
+    }
+
+    pub fn time(&self) -> f64 {
+        self.current().map_or(0.0, |frame| frame.time)
+    }
+
+    pub fn step_count(&self) -> u64 {
+        self.current().map_or(0, |frame| frame.step_count)
+    }
+
+    pub fn ids(&self) -> Vec<i32> {
+        self.ids.clone()
+    }
+
+    pub fn positions_x(&self) -> Vec<f32> {
+        self.channel(0)
+    }
+
+    pub fn positions_y(&self) -> Vec<f32> {
+        self.channel(1)
+    }
+
+    /// Empty unless velocities were recorded.
+    pub fn velocities_x(&self) -> Vec<f32> {
+        self.channel(2)
+    }
+
+    pub fn velocities_y(&self) -> Vec<f32> {
+        self.channel(3)
+    }
+}
+
+impl TrajectoryPlayer {
+    /// Plays back a trajectory saved with `TrajectoryRecorder::to_bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<TrajectoryPlayer, TrajectoryError> {
+        Ok(TrajectoryPlayer::new(Trajectory::from_bytes(bytes)?))
+    }
+
+    fn new(trajectory: Trajectory) -> TrajectoryPlayer {
+        TrajectoryPlayer {
+            trajectory,
+            frame: None,
+            ids: Vec::new(),
+            values: Vec::new(),
+        }
+    }
+
+    fn current(&self) -> Option<&FrameInfo> {
+        self.frame.map(|i| &self.trajectory.frames[i])
+    }
+
+    fn channel(&self, channel: usize) -> Vec<f32> {
+        let count = self.ids.len();
+        self.values
+            .get(channel * count..(channel + 1) * count)
+            .unwrap_or_default()
+            .iter()
+            .map(|&v| self.trajectory.dequantize(v))
+            .collect()
+    }
+
+    /// Applies frame `i` on top of the state of frame `i - 1`, or replaces
+    /// it for a keyframe.
+    fn decode(&self, i: usize, ids: &mut Vec<i32>, values: &mut Vec<i64>) -> bool {
+        let frame = &self.trajectory.frames[i];
+        let data = &self.trajectory.data;
+        let mut offset = frame.offset;
+        let len = frame.count * self.trajectory.channels();
+
+        if frame.keyframe {
+            let decoded: Option<Vec<i32>> = (0..frame.count)
+                .map(|_| read_varint(data, &mut offset).map(|id| id as i32))
+                .collect();
+            let Some(decoded) = decoded else {
+                return false;
+            };
+            *ids = decoded;
+            values.clear();
+            values.resize(len, 0);
+        } else if values.len() != len {
+            return false;
+        }
+
+        for value in values.iter_mut() {
+            let Some(delta) = read_varint(data, &mut offset) else {
+                return false;
+            };
+            *value = value.wrapping_add(delta);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::particle::Particle;
+    use crate::vector2::Vector2;
+
+    fn simulation() -> Simulation {
+        Simulation::new(
+            (0..20)
+                .map(|i| {
+                    let angle = i as f32 * 0.3;
+                    Particle::new(
+                        1.0,
+                        1.0,
+                        Vector2::new(50.0 + 20.0 * angle.cos(), 50.0 + 20.0 * angle.sin()),
+                        Vector2::new(-angle.sin(), angle.cos()),
+                        [255.0, 255.0, 255.0],
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    fn step(sim: &mut Simulation) {
+        sim.step(Vector2::new(100.0, 100.0), 1.0, 0.5, 0.01);
+    }
+
+    #[test]
+    fn test_varint_round_trip() {
+        let mut data = Vec::new();
+        let values = [0, -1, 1, 63, -64, 64, i64::MAX, i64::MIN];
+        for value in values {
+            write_varint(&mut data, value);
+        }
+        assert_eq!(data[..3], [0, 1, 2]);
+
+        let mut offset = 0;
+        for value in values {
+            assert_eq!(read_varint(&data, &mut offset), Some(value));
+        }
+        assert_eq!(read_varint(&data, &mut offset), None);
+    }
+
+    #[test]
+    fn test_frames_come_back_within_precision() {
+        let mut sim = simulation();
+        let mut recorder = TrajectoryRecorder::new(2, 0.001, true, 4).unwrap();
+        let mut expected = Vec::new();
+
+        for _ in 0..=20 {
+            if recorder.record(&sim) {
+                expected.push((sim.positions_x.clone(), sim.velocities_y.clone()));
+            }
+            step(&mut sim);
+        }
+        assert_eq!(recorder.frames(), 11);
+        // Two coordinates take at most 3 bytes each between keyframes, against 8 raw
+        assert!(recorder.encoded_len() < 11 * 20 * 4 * 4);
+
+        let mut player = recorder.player();
+        for frame in [10, 3, 4, 0, 7] {
+            assert!(player.seek(frame));
+            let (positions_x, velocities_y) = &expected[frame];
+            for (a, b) in player.positions_x().iter().zip(positions_x) {
+                assert!((a - b).abs() <= 0.0005 + 1e-5);
+            }
+            for (a, b) in player.velocities_y().iter().zip(velocities_y) {
+                assert!((a - b).abs() <= 0.0005 + 1e-5);
+            }
+            assert_eq!(player.step_count(), frame as u64 * 2);
+        }
+        assert_eq!(player.ids(), sim.ids);
+        assert!(!player.seek(11));
+        assert_eq!(player.frame(), Some(7));
+    }
+
+    #[test]
+    fn test_particle_count_change_forces_keyframe() {
+        let mut sim = simulation();
+        let mut recorder = TrajectoryRecorder::new(1, 0.01, false, 100).unwrap();
+        recorder.record(&sim);
+        step(&mut sim);
+        recorder.record(&sim);
+
+        let mut smaller = Simulation::new(vec![sim.particle(0)]);
+        smaller.step_count = 2;
+        recorder.record(&smaller);
+
+        let keyframes: Vec<bool> = recorder
+            .trajectory
+            .frames
+            .iter()
+            .map(|f| f.keyframe)
+            .collect();
+        assert_eq!(keyframes, vec![true, false, true]);
+
+        let mut player = recorder.player();
+        assert!(player.seek(2));
+        assert_eq!(player.ids(), vec![sim.ids[0]]);
+        assert!(player.velocities_x().is_empty());
+    }
+
+    #[test]
+    fn test_different_particles_force_keyframe() {
+        let mut sim = simulation();
+        let mut recorder = TrajectoryRecorder::new(1, 0.01, false, 100).unwrap();
+        recorder.record(&sim);
+        step(&mut sim);
+        recorder.record(&sim);
+
+        // Same count, other particles, as after loading another scene
+        let mut other = simulation();
+        other.set_ids((100..120).collect());
+        other.step_count = 2;
+        recorder.record(&other);
+        step(&mut other);
+        recorder.record(&other);
+
+        let keyframes: Vec<bool> = recorder
+            .trajectory
+            .frames
+            .iter()
+            .map(|f| f.keyframe)
+            .collect();
+        assert_eq!(keyframes, vec![true, false, true, false]);
+
+        let mut player = recorder.player();
+        assert!(player.seek(3));
+        assert_eq!(player.ids(), other.ids);
+        assert!((player.positions_x()[0] - other.positions_x[0]).abs() <= 0.005 + 1e-5);
+    }
+
+    #[test]
+    fn test_invalid_precision_is_rejected() {
+        for precision in [0.0, -0.01, f32::NAN, f32::INFINITY] {
+            assert_eq!(
+                TrajectoryRecorder::new(1, precision, false, 10).unwrap_err(),
+                TrajectoryError::InvalidPrecision
+            );
+        }
+    }
+
+    #[test]
+    fn test_saved_trajectory_plays_back() {
+        let mut sim = simulation();
+        let mut recorder = TrajectoryRecorder::new(1, 0.01, false, 3).unwrap();
+        for _ in 0..5 {
+            recorder.record(&sim);
+            step(&mut sim);
+        }
+
+        let bytes = recorder.to_bytes();
+        let mut saved = TrajectoryPlayer::from_bytes(&bytes).unwrap();
+        let mut live = recorder.player();
+        assert!(saved.seek(4) && live.seek(4));
+        assert_eq!(saved.positions_y(), live.positions_y());
+        assert_eq!(saved.time(), live.time());
+
+        let mut flipped = bytes.clone();
+        flipped[30] ^= 1;
+        assert_eq!(
+            TrajectoryPlayer::from_bytes(&flipped).unwrap_err(),
+            TrajectoryError::ChecksumMismatch
+        );
+        assert_eq!(
+            TrajectoryPlayer::from_bytes(b"NBODYSIM").unwrap_err(),
+            TrajectoryError::BadMagic
+        );
+    }
+}
@@ -0,0 +1,251 @@
+/// Byte order of the numbers in a buffer. Formats are little-endian unless
+/// they say otherwise.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Endian {
+    #[default]
+    Little,
+    Big,
+}
+
+impl Endian {
+    pub fn native() -> Endian {
+        if cfg!(target_endian = "big") {
+            Endian::Big
+        } else {
+            Endian::Little
+        }
+    }
+
+    /// Turns little-endian bytes into this order, or back.
+    fn order<const N: usize>(self, mut bytes: [u8; N]) -> [u8; N] {
+        if self == Endian::Big {
+            bytes.reverse();
+        }
+        bytes
+    }
+}
+
+/// Appends numbers to a growing byte buffer, little-endian by default.
+#[derive(Debug, Default)]
+pub struct ByteWriter {
+    bytes: Vec<u8>,
+    endian: Endian,
+}
+
+impl ByteWriter {
+    pub fn new() -> ByteWriter {
+        ByteWriter::default()
+    }
+
+    pub fn with_endian(endian: Endian) -> ByteWriter {
+        ByteWriter {
+            bytes: Vec::new(),
+            endian,
+        }
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    pub fn write_zeros(&mut self, len: usize) {
+        self.bytes.resize(self.bytes.len() + len, 0);
+    }
+
+    pub fn write_i32(&mut self, value: i32) {
+        self.write_bytes(&self.endian.order(value.to_le_bytes()));
+    }
+
+    pub fn write_u16(&mut self, value: u16) {
+        self.write_bytes(&self.endian.order(value.to_le_bytes()));
+    }
+
+    pub fn write_u32(&mut self, value: u32) {
+        self.write_bytes(&self.endian.order(value.to_le_bytes()));
+    }
+
+    pub fn write_u64(&mut self, value: u64) {
+        self.write_bytes(&self.endian.order(value.to_le_bytes()));
+    }
+
+    pub fn write_f32(&mut self, value: f32) {
+        self.write_bytes(&self.endian.order(value.to_le_bytes()));
+    }
+
+    pub fn write_f64(&mut self, value: f64) {
+        self.write_bytes(&self.endian.order(value.to_le_bytes()));
+    }
+
+    pub fn write_i32_slice(&mut self, values: &[i32]) {
+        for &value in values {
+            self.write_i32(value);
+        }
+    }
+
+    pub fn write_f32_slice(&mut self, values: &[f32]) {
+        for &value in values {
+            self.write_f32(value);
+        }
+    }
+
+    pub fn write_f64_slice(&mut self, values: &[f64]) {
+        for &value in values {
+            self.write_f64(value);
+        }
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+/// Reads numbers from a byte slice, little-endian by default. Every read
+/// returns `None` once the input runs out, leaving the caller to name the
+/// error.
+#[derive(Debug)]
+pub struct ByteReader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    endian: Endian,
+}
+
+impl<'a> ByteReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> ByteReader<'a> {
+        ByteReader::with_endian(bytes, Endian::Little)
+    }
+
+    pub fn with_endian(bytes: &'a [u8], endian: Endian) -> ByteReader<'a> {
+        ByteReader {
+            bytes,
+            offset: 0,
+            endian,
+        }
+    }
+
+    pub fn read_bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let end = self.offset.checked_add(len)?;
+        let bytes = self.bytes.get(self.offset..end)?;
+        self.offset = end;
+        Some(bytes)
+    }
+
+    /// The next `N` bytes, put in little-endian order.
+    fn read_array<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let bytes = self.read_bytes(N)?.try_into().ok()?;
+        Some(self.endian.order(bytes))
+    }
+
+    pub fn read_i32(&mut self) -> Option<i32> {
+        self.read_array().map(i32::from_le_bytes)
+    }
+
+    pub fn read_u32(&mut self) -> Option<u32> {
+        self.read_array().map(u32::from_le_bytes)
+    }
+
+    pub fn read_u64(&mut self) -> Option<u64> {
+        self.read_array().map(u64::from_le_bytes)
+    }
+
+    pub fn read_f32(&mut self) -> Option<f32> {
+        self.read_array().map(f32::from_le_bytes)
+    }
+
+    pub fn read_f64(&mut self) -> Option<f64> {
+        self.read_array().map(f64::from_le_bytes)
+    }
+
+    pub fn read_i32_vec(&mut self, len: usize) -> Option<Vec<i32>> {
+        let bytes = self.read_bytes(len.checked_mul(4)?)?;
+        Some(
+            bytes
+                .chunks_exact(4)
+                .map(|chunk| i32::from_le_bytes(self.endian.order(chunk.try_into().unwrap())))
+                .collect(),
+        )
+    }
+
+    pub fn read_f32_vec(&mut self, len: usize) -> Option<Vec<f32>> {
+        let bytes = self.read_bytes(len.checked_mul(4)?)?;
+        Some(
+            bytes
+                .chunks_exact(4)
+                .map(|chunk| f32::from_le_bytes(self.endian.order(chunk.try_into().unwrap())))
+                .collect(),
+        )
+    }
+
+    pub fn read_f64_vec(&mut self, len: usize) -> Option<Vec<f64>> {
+        let bytes = self.read_bytes(len.checked_mul(8)?)?;
+        Some(
+            bytes
+                .chunks_exact(8)
+                .map(|chunk| f64::from_le_bytes(self.endian.order(chunk.try_into().unwrap())))
+                .collect(),
+        )
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.bytes.len() - self.offset
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut writer = ByteWriter::new();
+        writer.write_bytes(b"AB");
+        writer.write_u32(7);
+        writer.write_u64(u64::MAX - 1);
+        writer.write_f32(-1.5);
+        writer.write_i32(-9);
+        writer.write_f64(0.1);
+        writer.write_zeros(3);
+        writer.write_i32_slice(&[-3, 4]);
+        writer.write_f32_slice(&[0.25, 8.0]);
+        writer.write_f64_slice(&[1e-300]);
+        let bytes = writer.into_bytes();
+
+        let mut reader = ByteReader::new(&bytes);
+        assert_eq!(reader.read_bytes(2), Some(&b"AB"[..]));
+        assert_eq!(reader.read_u32(), Some(7));
+        assert_eq!(reader.read_u64(), Some(u64::MAX - 1));
+        assert_eq!(reader.read_f32(), Some(-1.5));
+        assert_eq!(reader.read_i32(), Some(-9));
+        assert_eq!(reader.read_f64(), Some(0.1));
+        assert_eq!(reader.read_bytes(3), Some(&[0, 0, 0][..]));
+        assert_eq!(reader.read_i32_vec(2), Some(vec![-3, 4]));
+        assert_eq!(reader.read_f32_vec(2), Some(vec![0.25, 8.0]));
+        assert_eq!(reader.read_f64_vec(1), Some(vec![1e-300]));
+        assert_eq!(reader.remaining(), 0);
+        assert_eq!(reader.read_u32(), None);
+    }
+
+    #[test]
+    fn test_big_endian() {
+        let mut writer = ByteWriter::with_endian(Endian::Big);
+        writer.write_u32(0x0102_0304);
+        writer.write_f32_slice(&[1.0]);
+        let bytes = writer.into_bytes();
+        assert_eq!(bytes, [1, 2, 3, 4, 0x3f, 0x80, 0, 0]);
+
+        let mut reader = ByteReader::with_endian(&bytes, Endian::Big);
+        assert_eq!(reader.read_u32(), Some(0x0102_0304));
+        assert_eq!(reader.read_f32_vec(1), Some(vec![1.0]));
+    }
+
+    #[test]
+    fn test_oversized_length_is_rejected() {
+        let mut reader = ByteReader::new(&[0; 8]);
+        assert_eq!(reader.read_f32_vec(usize::MAX), None);
+        assert_eq!(reader.read_f32_vec(3), None);
+        assert_eq!(reader.remaining(), 8);
+    }
+}
@@ -22,7 +22,77 @@ pub fn softened_gravitational_force(
     let force = distance_vector.scale(force_magnitude_scaled);
     
     // Acceleration a = F / m
-    return force.scale(scale / p2.mass);
+    force.scale(scale / p2.mass)
+}
+
+/// Softened acceleration at `p_pos` due to a point mass, already multiplied by
+/// `scale` (the time step when used as a velocity kick).
+pub fn softened_acceleration(
+    p_pos: Vector2,
+    other_pos: Vector2,
+    other_mass: f32,
+    gravity: f32,
+    epsilon: f32,
+    scale: f32,
+) -> Vector2 {
+    if p_pos.x == other_pos.x && p_pos.y == other_pos.y {
+        return Vector2::new(0.0, 0.0);
+    }
+
+    let distance_vector = other_pos - p_pos; // Vector from self (p2) to other (p1)
+    let r_sq = distance_vector.x.powi(2) + distance_vector.y.powi(2);
+    let force_magnitude_scaled = gravity * other_mass / (r_sq + epsilon.powi(2)).powf(1.5);
+
+    // Acceleration = (G * m1 * r_vec) / (r^2 + eps^2)^1.5 * scale
+    distance_vector.scale(force_magnitude_scaled * scale)
+}
+
+/// Softened potential at `p_pos` due to a point mass, `-G m / sqrt(r^2 + eps^2)`.
+pub fn softened_potential(
+    p_pos: Vector2,
+    other_pos: Vector2,
+    other_mass: f32,
+    gravity: f32,
+    epsilon: f32,
+) -> f64 {
+    // Same self-interaction rule as softened_acceleration
+    if p_pos.x == other_pos.x && p_pos.y == other_pos.y {
+        return 0.0;
+    }
+
+    let dx = (other_pos.x - p_pos.x) as f64;
+    let dy = (other_pos.y - p_pos.y) as f64;
+    let r = (dx * dx + dy * dy + (epsilon as f64).powi(2)).sqrt();
+    -(gravity as f64) * other_mass as f64 / r
+}
+
+/// Total softened potential energy by direct summation over all pairs,
+/// accumulated in f64 since it is a sum of many large, cancelling terms.
+pub fn potential_energy(
+    pos_x: &[f32],
+    pos_y: &[f32],
+    masses: &[f32],
+    gravity: f32,
+    epsilon: f32,
+) -> f64 {
+    let epsilon_sq = (epsilon as f64).powi(2);
+    let mut energy = 0.0;
+
+    for i in 0..masses.len() {
+        for j in (i + 1)..masses.len() {
+            // Coincident particles don't interact, as in softened_potential
+            if pos_x[i] == pos_x[j] && pos_y[i] == pos_y[j] {
+                continue;
+            }
+
+            let dx = (pos_x[i] - pos_x[j]) as f64;
+            let dy = (pos_y[i] - pos_y[j]) as f64;
+            let r = (dx * dx + dy * dy + epsilon_sq).sqrt();
+            energy -= masses[i] as f64 * masses[j] as f64 / r;
+        }
+    }
+
+    gravity as f64 * energy
 }
 
 #[cfg(test)]
@@ -60,4 +130,17 @@ mod tests {
         assert!((result_acceleration.x - expected_acceleration.x).abs() < 1e-1);
         assert!((result_acceleration.y - expected_acceleration.y).abs() < 1e-1);
     }
+
+    #[test]
+    fn test_potential_energy() {
+        let pos_x = [0.0, 3.0, 0.0];
+        let pos_y = [0.0, 0.0, 4.0];
+        let masses = [1.0, 2.0, 3.0];
+
+        // -G (1*2/3 + 1*3/4 + 2*3/5)
+        let expected = -2.0 * (2.0 / 3.0 + 3.0 / 4.0 + 6.0 / 5.0);
+        let energy = potential_energy(&pos_x, &pos_y, &masses, 2.0, 0.0);
+
+        assert!((energy - expected).abs() < 1e-9);
+    }
 }
@@ -1,2 +1,7 @@
+pub mod binary_utils;
 pub mod calculation_utils;
+pub mod checksum;
 pub mod quadrant_utils;
+pub mod orbit_utils;
+pub mod random_utils;
+pub mod zip_utils;
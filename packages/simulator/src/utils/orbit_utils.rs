@@ -0,0 +1,99 @@
+use std::f64::consts::PI;
+
+const KEPLER_TOLERANCE: f64 = 1e-12;
+const KEPLER_MAX_ITERATIONS: u32 = 50;
+
+/// Solves Kepler's equation `M = E - e sin(E)` for the eccentric anomaly `E`
+/// of an elliptical orbit (0 <= e < 1) using Newton's method.
+pub fn solve_kepler(mean_anomaly: f64, eccentricity: f64) -> f64 {
+    let m = (mean_anomaly + PI).rem_euclid(2.0 * PI) - PI;
+
+    // Starting at pi converges for every eccentricity, M alone is fine for low ones
+    let mut e_anomaly = if eccentricity < 0.8 {
+        m
+    } else {
+        PI.copysign(m)
+    };
+
+    for _ in 0..KEPLER_MAX_ITERATIONS {
+        let f = e_anomaly - eccentricity * e_anomaly.sin() - m;
+        let delta = f / (1.0 - eccentricity * e_anomaly.cos());
+        e_anomaly -= delta;
+
+        if delta.abs() < KEPLER_TOLERANCE {
+            break;
+        }
+    }
+
+    e_anomaly
+}
+
+/// Semi-major axis, eccentricity and argument of periapsis of the relative
+/// orbit with separation `(rx, ry)` and relative velocity `(vx, vy)`.
+/// Unbound orbits have a negative semi-major axis and `e >= 1`.
+pub fn elements_from_state(rx: f64, ry: f64, vx: f64, vy: f64, mu: f64) -> (f64, f64, f64) {
+    let r = (rx * rx + ry * ry).sqrt();
+    let v_sq = vx * vx + vy * vy;
+    let r_dot_v = rx * vx + ry * vy;
+
+    let specific_energy = v_sq / 2.0 - mu / r;
+    let semi_major_axis = -mu / (2.0 * specific_energy);
+
+    // Eccentricity vector points at periapsis
+    let ex = ((v_sq - mu / r) * rx - r_dot_v * vx) / mu;
+    let ey = ((v_sq - mu / r) * ry - r_dot_v * vy) / mu;
+    let eccentricity = (ex * ex + ey * ey).sqrt();
+
+    let argument_of_periapsis = if eccentricity > 1e-9 {
+        ey.atan2(ex)
+    } else {
+        0.0
+    };
+
+    (semi_major_axis, eccentricity, argument_of_periapsis)
+}
+
+/// Orbital period of a bound orbit.
+pub fn orbital_period(semi_major_axis: f64, mu: f64) -> f64 {
+    2.0 * PI * (semi_major_axis.powi(3) / mu).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solve_kepler_circular() {
+        let e_anomaly = solve_kepler(1.0, 0.0);
+        assert!((e_anomaly - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_elements_from_circular_state() {
+        // r = 10, v = sqrt(mu / r)
+        let (a, e, _) = elements_from_state(10.0, 0.0, 0.0, 10.0_f64.sqrt(), 100.0);
+
+        assert!((a - 10.0).abs() < 1e-9);
+        assert!(e < 1e-9);
+        assert!((orbital_period(a, 100.0) - 2.0 * PI * 10.0_f64.sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_elements_from_unbound_state() {
+        let (a, e, _) = elements_from_state(10.0, 0.0, 0.0, 10.0, 100.0);
+
+        assert!(a < 0.0);
+        assert!(e > 1.0);
+    }
+
+    #[test]
+    fn test_solve_kepler_satisfies_equation() {
+        for &e in &[0.1, 0.5, 0.9, 0.99] {
+            for &m in &[-3.0, -1.0, 0.0, 0.5, 2.0, 3.1] {
+                let e_anomaly = solve_kepler(m, e);
+                let residual = e_anomaly - e * e_anomaly.sin() - m;
+                assert!(residual.abs() < 1e-9, "e = {}, M = {}", e, m);
+            }
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use rand::Rng;
+
+/// Standard normal deviate via the Box-Muller transform.
+pub fn standard_normal<R: Rng + ?Sized>(rng: &mut R) -> f32 {
+    let u1 = 1.0 - rng.gen::<f32>(); // (0, 1], keeps ln finite
+    let u2 = rng.gen::<f32>();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f32::consts::PI * u2).cos()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_standard_normal_moments() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let n = 50_000;
+        let values: Vec<f32> = (0..n).map(|_| standard_normal(&mut rng)).collect();
+
+        let mean = values.iter().sum::<f32>() / n as f32;
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / n as f32;
+
+        assert!(mean.abs() < 0.02);
+        assert!((variance - 1.0).abs() < 0.03);
+    }
+}
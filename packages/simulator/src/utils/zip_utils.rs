@@ -0,0 +1,154 @@
+use std::fmt;
+
+use crate::utils::binary_utils::ByteWriter;
+use crate::utils::checksum::crc32;
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0605_4b50;
+
+/// Zip 2.0, the oldest version that readers still expect.
+const ZIP_VERSION: u16 = 20;
+
+/// MS-DOS date for 1980-01-01, the earliest a zip entry can carry.
+const DOS_EPOCH_DATE: u16 = (1 << 5) | 1;
+
+/// Limits of the zip format without Zip64.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ZipError {
+    TooManyEntries,
+    NameTooLong,
+    /// An entry, offset or the central directory past 4 GiB
+    TooLarge,
+}
+
+impl fmt::Display for ZipError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ZipError::TooManyEntries => write!(f, "Zip archives hold at most 65535 entries"),
+            ZipError::NameTooLong => write!(f, "Zip entry names must be under 64 KiB"),
+            ZipError::TooLarge => write!(f, "Zip entries and archives must stay under 4 GiB"),
+        }
+    }
+}
+
+fn zip_u32(value: usize) -> Result<u32, ZipError> {
+    u32::try_from(value).map_err(|_| ZipError::TooLarge)
+}
+
+/// Fields shared by the local and central headers, from the version needed
+/// to the file name length. Entries are stored uncompressed, so both sizes
+/// are the data length.
+fn write_entry_fields(writer: &mut ByteWriter, name: &str, data: &[u8]) -> Result<(), ZipError> {
+    let size = zip_u32(data.len())?;
+    let name_length = u16::try_from(name.len()).map_err(|_| ZipError::NameTooLong)?;
+
+    writer.write_u16(ZIP_VERSION);
+    // No flags, stored, midnight
+    writer.write_u16(0);
+    writer.write_u16(0);
+    writer.write_u16(0);
+    writer.write_u16(DOS_EPOCH_DATE);
+    writer.write_u32(crc32(data));
+    writer.write_u32(size);
+    writer.write_u32(size);
+    writer.write_u16(name_length);
+    Ok(())
+}
+
+/// Packs named files into an uncompressed zip archive. There is no Zip64
+/// support, so the archive must stay under 4 GiB and 65535 entries.
+pub fn stored_zip<S: AsRef<str>>(entries: &[(S, Vec<u8>)]) -> Result<Vec<u8>, ZipError> {
+    let entry_count = u16::try_from(entries.len()).map_err(|_| ZipError::TooManyEntries)?;
+    let mut writer = ByteWriter::new();
+    let mut offsets = Vec::with_capacity(entries.len());
+
+    for (name, data) in entries {
+        let name = name.as_ref();
+        offsets.push(zip_u32(writer.bytes().len())?);
+        writer.write_u32(LOCAL_HEADER_SIGNATURE);
+        write_entry_fields(&mut writer, name, data)?;
+        // No extra field
+        writer.write_u16(0);
+        writer.write_bytes(name.as_bytes());
+        writer.write_bytes(data);
+    }
+
+    let directory_offset = zip_u32(writer.bytes().len())?;
+    for ((name, data), offset) in entries.iter().zip(offsets) {
+        let name = name.as_ref();
+        writer.write_u32(CENTRAL_HEADER_SIGNATURE);
+        writer.write_u16(ZIP_VERSION);
+        write_entry_fields(&mut writer, name, data)?;
+        // Extra field, comment, disk number, internal and external attributes
+        writer.write_zeros(2 + 2 + 2 + 2 + 4);
+        writer.write_u32(offset);
+        writer.write_bytes(name.as_bytes());
+    }
+    let directory_size = zip_u32(writer.bytes().len())? - directory_offset;
+
+    writer.write_u32(END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+    // This disk and the one the directory starts on
+    writer.write_zeros(4);
+    writer.write_u16(entry_count);
+    writer.write_u16(entry_count);
+    writer.write_u32(directory_size);
+    writer.write_u32(directory_offset);
+    // No comment
+    writer.write_u16(0);
+
+    Ok(writer.into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::binary_utils::ByteReader;
+
+    #[test]
+    fn test_layout() {
+        let zip = stored_zip(&[("a.txt", b"hello".to_vec()), ("b", Vec::new())]).unwrap();
+
+        let mut reader = ByteReader::new(&zip);
+        assert_eq!(reader.read_u32(), Some(LOCAL_HEADER_SIGNATURE));
+        reader.read_bytes(10);
+        assert_eq!(reader.read_u32(), Some(crc32(b"hello")));
+        assert_eq!(reader.read_u32(), Some(5));
+        assert_eq!(reader.read_u32(), Some(5));
+        reader.read_bytes(4);
+        assert_eq!(reader.read_bytes(5), Some(&b"a.txt"[..]));
+        assert_eq!(reader.read_bytes(5), Some(&b"hello"[..]));
+        assert_eq!(reader.read_u32(), Some(LOCAL_HEADER_SIGNATURE));
+
+        // The end record points back at both central entries
+        let end = zip.len() - 22;
+        let mut reader = ByteReader::new(&zip[end..]);
+        assert_eq!(reader.read_u32(), Some(END_OF_CENTRAL_DIRECTORY_SIGNATURE));
+        reader.read_bytes(6);
+        assert_eq!(reader.read_bytes(2), Some(&[2, 0][..]));
+        let directory_size = reader.read_u32().unwrap() as usize;
+        let directory_offset = reader.read_u32().unwrap() as usize;
+        assert_eq!(directory_offset + directory_size, end);
+        assert_eq!(
+            &zip[directory_offset..directory_offset + 4],
+            &CENTRAL_HEADER_SIGNATURE.to_le_bytes()
+        );
+    }
+    #[test]
+    fn test_limits_without_zip64() {
+        let entries = vec![("x", Vec::new()); u16::MAX as usize + 1];
+        assert_eq!(stored_zip(&entries), Err(ZipError::TooManyEntries));
+        assert!(stored_zip(&entries[1..]).is_ok());
+
+        let name = "x".repeat(u16::MAX as usize + 1);
+        assert_eq!(
+            stored_zip(&[(name, Vec::new())]),
+            Err(ZipError::NameTooLong)
+        );
+
+        assert_eq!(zip_u32(u32::MAX as usize), Ok(u32::MAX));
+        if let Ok(too_large) = usize::try_from(u32::MAX as u64 + 1) {
+            assert_eq!(zip_u32(too_large), Err(ZipError::TooLarge));
+        }
+    }
+}
@@ -2,20 +2,21 @@ use std::fmt;
 
 use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 #[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct Vector2 {
     pub x: f32,
     pub y: f32,
 }
 
-#[wasm_bindgen]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 impl Vector2 {
-    #[wasm_bindgen(constructor)]
+    #[cfg_attr(feature = "wasm", wasm_bindgen(constructor))]
     pub fn new(x: f32, y: f32) -> Vector2 {
-        return Vector2 { x, y };
+        Vector2 { x, y }
     }
 
     pub fn distance(&self, other: &Vector2) -> f32 {
@@ -0,0 +1,311 @@
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::diagnostics::PotentialMethod;
+use crate::particle::Particle;
+use crate::simulation::Simulation;
+use crate::utils::random_utils::standard_normal;
+use crate::vector2::Vector2;
+
+/// Above this many particles the virial scaling sums the potential with the
+/// Barnes-Hut tree instead of over every pair.
+const TREE_POTENTIAL_THRESHOLD: usize = 2000;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum VelocityLaw {
+    Cold,
+    Virial {
+        ratio: f32,
+        gravity: f32,
+        epsilon: f32,
+    },
+    SolidBody {
+        angular_velocity: f32,
+    },
+    Keplerian {
+        gravity: f32,
+    },
+    Hubble {
+        hubble_constant: f32,
+    },
+}
+
+/// How initial velocities are assigned once every position is known.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct VelocityProfile {
+    law: VelocityLaw,
+}
+
+impl Default for VelocityProfile {
+    fn default() -> Self {
+        VelocityProfile::cold()
+    }
+}
+
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+impl VelocityProfile {
+    /// Everything starts at rest.
+    pub fn cold() -> VelocityProfile {
+        VelocityProfile {
+            law: VelocityLaw::Cold,
+        }
+    }
+
+    /// Isotropic Maxwellian velocities scaled so that the virial ratio
+    /// `K / |W|` equals `ratio`; 0.5 is equilibrium, below collapses.
+    pub fn virial(ratio: f32, gravity: f32, epsilon: f32) -> VelocityProfile {
+        VelocityProfile {
+            law: VelocityLaw::Virial {
+                ratio,
+                gravity,
+                epsilon,
+            },
+        }
+    }
+
+    /// Rigid rotation about the center of mass, `v = ω × r`.
+    pub fn solid_body(angular_velocity: f32) -> VelocityProfile {
+        VelocityProfile {
+            law: VelocityLaw::SolidBody { angular_velocity },
+        }
+    }
+
+    /// Circular speed from the mass enclosed within each particle's radius.
+    pub fn keplerian(gravity: f32) -> VelocityProfile {
+        VelocityProfile {
+            law: VelocityLaw::Keplerian { gravity },
+        }
+    }
+
+    /// Radial expansion away from the center of mass, `v = H r`.
+    pub fn hubble(hubble_constant: f32) -> VelocityProfile {
+        VelocityProfile {
+            law: VelocityLaw::Hubble { hubble_constant },
+        }
+    }
+}
+
+impl VelocityProfile {
+    /// Overwrites the velocity of every particle.
+    pub fn apply(&self, particles: &mut [Particle]) {
+        if particles.is_empty() {
+            return;
+        }
+
+        let center = center_of_mass(particles);
+
+        match self.law {
+            VelocityLaw::Cold => {
+                for p in particles.iter_mut() {
+                    p.velocity = Vector2::new(0.0, 0.0);
+                }
+            }
+            VelocityLaw::Virial {
+                ratio,
+                gravity,
+                epsilon,
+            } => apply_virial(particles, ratio, gravity, epsilon),
+            VelocityLaw::SolidBody { angular_velocity } => {
+                for p in particles.iter_mut() {
+                    let r = p.position - center;
+                    p.velocity = Vector2::new(-r.y, r.x).scale(angular_velocity);
+                }
+            }
+            VelocityLaw::Keplerian { gravity } => apply_keplerian(particles, center, gravity),
+            VelocityLaw::Hubble { hubble_constant } => {
+                for p in particles.iter_mut() {
+                    p.velocity = (p.position - center).scale(hubble_constant);
+                }
+            }
+        }
+    }
+}
+
+fn center_of_mass(particles: &[Particle]) -> Vector2 {
+    let total_mass: f32 = particles.iter().map(|p| p.mass).sum();
+    if total_mass == 0.0 {
+        return Vector2::new(0.0, 0.0);
+    }
+
+    let weighted = particles.iter().fold(Vector2::new(0.0, 0.0), |acc, p| {
+        acc + p.position.scale(p.mass)
+    });
+    weighted.scale(1.0 / total_mass)
+}
+
+fn apply_virial(particles: &mut [Particle], ratio: f32, gravity: f32, epsilon: f32) {
+    let mut rng = rand::thread_rng();
+
+    for p in particles.iter_mut() {
+        p.velocity = Vector2::new(standard_normal(&mut rng), standard_normal(&mut rng));
+    }
+
+    // Remove bulk motion so all kinetic energy is internal
+    let total_mass: f32 = particles.iter().map(|p| p.mass).sum();
+    let momentum = particles.iter().fold(Vector2::new(0.0, 0.0), |acc, p| {
+        acc + p.velocity.scale(p.mass)
+    });
+    if total_mass > 0.0 {
+        let drift = momentum.scale(1.0 / total_mass);
+        for p in particles.iter_mut() {
+            p.velocity = p.velocity - drift;
+        }
+    }
+
+    let method = if particles.len() > TREE_POTENTIAL_THRESHOLD {
+        PotentialMethod::Tree
+    } else {
+        PotentialMethod::Direct
+    };
+    let potential = Simulation::new(particles.to_vec()).potential_energy(gravity, epsilon, method);
+
+    let kinetic: f64 = particles
+        .iter()
+        .map(|p| 0.5 * p.mass as f64 * (p.velocity.magnitude() as f64).powi(2))
+        .sum();
+
+    if kinetic > 0.0 {
+        let factor = (ratio as f64 * potential.abs() / kinetic).sqrt() as f32;
+        for p in particles.iter_mut() {
+            p.velocity = p.velocity.scale(factor);
+        }
+    }
+}
+
+fn apply_keplerian(particles: &mut [Particle], center: Vector2, gravity: f32) {
+    let mut order: Vec<usize> = (0..particles.len()).collect();
+    order.sort_by(|&a, &b| {
+        let ra = particles[a].position.distance(&center);
+        let rb = particles[b].position.distance(&center);
+        ra.total_cmp(&rb)
+    });
+
+    // Mass strictly inside each particle's radius
+    let mut enclosed_mass = 0.0;
+    for i in order {
+        let r = particles[i].position - center;
+        let radius = r.magnitude();
+
+        particles[i].velocity = if radius > 0.0 && enclosed_mass > 0.0 {
+            let speed = (gravity * enclosed_mass / radius).sqrt();
+            Vector2::new(-r.y, r.x).scale(speed / radius)
+        } else {
+            Vector2::new(0.0, 0.0)
+        };
+
+        enclosed_mass += particles[i].mass;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::calculation_utils::potential_energy;
+    use rand::prelude::*;
+
+    fn ring(n: usize, radius: f32) -> Vec<Particle> {
+        let mut particles = vec![Particle::new(
+            100.0,
+            1.0,
+            Vector2::new(0.0, 0.0),
+            Vector2::new(0.0, 0.0),
+            [255.0, 255.0, 255.0],
+        )];
+
+        for i in 0..n {
+            let angle = i as f32 / n as f32 * std::f32::consts::TAU;
+            particles.push(Particle::new(
+                1.0,
+                1.0,
+                Vector2::new(radius * angle.cos(), radius * angle.sin()),
+                Vector2::new(0.0, 0.0),
+                [255.0, 255.0, 255.0],
+            ));
+        }
+        particles
+    }
+
+    #[test]
+    fn test_virial_ratio_is_reached() {
+        let mut particles = ring(50, 10.0);
+        VelocityProfile::virial(0.5, 1.0, 0.1).apply(&mut particles);
+
+        let pos_x: Vec<f32> = particles.iter().map(|p| p.position.x).collect();
+        let pos_y: Vec<f32> = particles.iter().map(|p| p.position.y).collect();
+        let masses: Vec<f32> = particles.iter().map(|p| p.mass).collect();
+        let potential = potential_energy(&pos_x, &pos_y, &masses, 1.0, 0.1);
+        let kinetic: f64 = particles
+            .iter()
+            .map(|p| 0.5 * p.mass as f64 * (p.velocity.magnitude() as f64).powi(2))
+            .sum();
+
+        assert!((kinetic / potential.abs() - 0.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_virial_ratio_with_tree_potential() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut particles: Vec<Particle> = (0..TREE_POTENTIAL_THRESHOLD + 500)
+            .map(|_| {
+                Particle::new(
+                    1.0,
+                    1.0,
+                    Vector2::new(rng.gen::<f32>() * 100.0, rng.gen::<f32>() * 100.0),
+                    Vector2::new(0.0, 0.0),
+                    [255.0, 255.0, 255.0],
+                )
+            })
+            .collect();
+        VelocityProfile::virial(0.5, 1.0, 0.1).apply(&mut particles);
+
+        let sim = Simulation::new(particles);
+        let potential = sim.potential_energy(1.0, 0.1, PotentialMethod::Direct);
+        let kinetic = sim.diagnostics(PotentialMethod::Direct).kinetic_energy;
+        // Off only by the tree's approximation of the potential
+        assert!((kinetic / potential.abs() - 0.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_virial_without_mass_stays_finite() {
+        let mut particles = ring(4, 10.0);
+        for p in particles.iter_mut() {
+            p.mass = 0.0;
+        }
+
+        VelocityProfile::virial(0.5, 1.0, 0.1).apply(&mut particles);
+
+        assert!(particles
+            .iter()
+            .all(|p| p.velocity.x.is_finite() && p.velocity.y.is_finite()));
+    }
+
+    #[test]
+    fn test_keplerian_speed_around_central_mass() {
+        let mut particles = ring(4, 10.0);
+        VelocityProfile::keplerian(1.0).apply(&mut particles);
+
+        assert_eq!(particles[0].velocity, Vector2::new(0.0, 0.0));
+        // Innermost ring particle only encloses the central mass
+        let speeds: Vec<f32> = particles[1..]
+            .iter()
+            .map(|p| p.velocity.magnitude())
+            .collect();
+        let min_speed = speeds.iter().cloned().fold(f32::INFINITY, f32::min);
+        assert!((min_speed - (100.0_f32 / 10.0).sqrt()).abs() < 1e-4);
+        // Tangential
+        let p = particles[1];
+        assert!((p.position.x * p.velocity.x + p.position.y * p.velocity.y).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_solid_body_and_hubble() {
+        let mut particles = ring(4, 10.0);
+
+        VelocityProfile::solid_body(2.0).apply(&mut particles);
+        assert_eq!(particles[1].velocity, Vector2::new(0.0, 20.0));
+
+        VelocityProfile::hubble(0.5).apply(&mut particles);
+        assert_eq!(particles[1].velocity, Vector2::new(5.0, 0.0));
+    }
+}
@@ -0,0 +1,260 @@
+use std::fmt::Display;
+
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
+
+use crate::simulation::Simulation;
+use crate::utils::binary_utils::{ByteWriter, Endian};
+
+/// VTK cell type of a single point.
+const VTK_VERTEX: u8 = 1;
+
+/// Files ParaView opens directly: the legacy `.vtk` format as text or as
+/// (big-endian) binary, or an XML `.vtu` unstructured grid.
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum VtkFormat {
+    LegacyAscii,
+    LegacyBinary,
+    #[default]
+    Xml,
+}
+
+/// Writes rows of numbers as text lines or, in legacy binary files, as raw
+/// big-endian values followed by a newline.
+fn write_rows<T: Display + Copy, const N: usize>(
+    writer: &mut ByteWriter,
+    binary: bool,
+    rows: impl Iterator<Item = [T; N]>,
+    write_value: fn(&mut ByteWriter, T),
+) {
+    for row in rows {
+        if binary {
+            row.iter().for_each(|&value| write_value(writer, value));
+        } else {
+            let line: Vec<String> = row.iter().map(|value| value.to_string()).collect();
+            writer.write_bytes(line.join(" ").as_bytes());
+            writer.write_bytes(b"\n");
+        }
+    }
+    if binary {
+        writer.write_bytes(b"\n");
+    }
+}
+
+fn write_line(writer: &mut ByteWriter, line: &str) {
+    writer.write_bytes(line.as_bytes());
+    writer.write_bytes(b"\n");
+}
+
+fn points(sim: &Simulation) -> impl Iterator<Item = [f32; 3]> + '_ {
+    (0..sim.count).map(|i| [sim.positions_x[i], sim.positions_y[i], 0.0])
+}
+
+fn velocity_vectors(sim: &Simulation) -> impl Iterator<Item = [f32; 3]> + '_ {
+    (0..sim.count).map(|i| [sim.velocities_x[i], sim.velocities_y[i], 0.0])
+}
+
+/// Space-separated values for an XML data array.
+fn join<T: Display>(values: impl Iterator<Item = T>) -> String {
+    values
+        .map(|value| value.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+impl Simulation {
+    /// Particles as VTK vertex cells with z = 0, carrying mass, velocity,
+    /// diameter and ID as point data and the simulation time as `TIME`.
+    pub fn to_vtk(&self, format: VtkFormat) -> Vec<u8> {
+        match format {
+            VtkFormat::LegacyAscii => self.to_legacy_vtk(false),
+            VtkFormat::LegacyBinary => self.to_legacy_vtk(true),
+            VtkFormat::Xml => self.to_vtu().into_bytes(),
+        }
+    }
+
+    fn to_legacy_vtk(&self, binary: bool) -> Vec<u8> {
+        let n = self.count;
+        let mut writer = ByteWriter::with_endian(Endian::Big);
+        let f32s = ByteWriter::write_f32;
+        let i32s = ByteWriter::write_i32;
+
+        write_line(&mut writer, "# vtk DataFile Version 3.0");
+        write_line(
+            &mut writer,
+            &format!("N-body snapshot, step {}", self.step_count),
+        );
+        write_line(&mut writer, if binary { "BINARY" } else { "ASCII" });
+        write_line(&mut writer, "DATASET UNSTRUCTURED_GRID");
+        write_line(&mut writer, "FIELD FieldData 1");
+        write_line(&mut writer, "TIME 1 1 double");
+        write_rows(
+            &mut writer,
+            binary,
+            [[self.time]].into_iter(),
+            ByteWriter::write_f64,
+        );
+
+        write_line(&mut writer, &format!("POINTS {} float", n));
+        write_rows(&mut writer, binary, points(self), f32s);
+        write_line(&mut writer, &format!("CELLS {} {}", n, 2 * n));
+        write_rows(&mut writer, binary, (0..n as i32).map(|i| [1, i]), i32s);
+        write_line(&mut writer, &format!("CELL_TYPES {}", n));
+        write_rows(
+            &mut writer,
+            binary,
+            (0..n).map(|_| [VTK_VERTEX as i32]),
+            i32s,
+        );
+
+        write_line(&mut writer, &format!("POINT_DATA {}", n));
+        for (name, values) in [("mass", &self.masses), ("diameter", &self.diameters)] {
+            write_line(&mut writer, &format!("SCALARS {} float 1", name));
+            write_line(&mut writer, "LOOKUP_TABLE default");
+            write_rows(&mut writer, binary, values.iter().map(|&v| [v]), f32s);
+        }
+        write_line(&mut writer, "VECTORS velocity float");
+        write_rows(&mut writer, binary, velocity_vectors(self), f32s);
+        write_line(&mut writer, "SCALARS id int 1");
+        write_line(&mut writer, "LOOKUP_TABLE default");
+        write_rows(&mut writer, binary, self.ids.iter().map(|&id| [id]), i32s);
+
+        writer.into_bytes()
+    }
+
+    fn to_vtu(&self) -> String {
+        let n = self.count;
+        let array = |kind: &str, name: &str, components: usize, values: String| {
+            format!(
+                "        <DataArray type=\"{}\" Name=\"{}\" NumberOfComponents=\"{}\" format=\"ascii\">{}</DataArray>\n",
+                kind, name, components, values
+            )
+        };
+
+        let mut xml = String::from("<?xml version=\"1.0\"?>\n");
+        xml.push_str(
+            "<VTKFile type=\"UnstructuredGrid\" version=\"0.1\" byte_order=\"LittleEndian\">\n",
+        );
+        xml.push_str("  <UnstructuredGrid>\n    <FieldData>\n");
+        xml.push_str(&format!(
+            "      <DataArray type=\"Float64\" Name=\"TIME\" NumberOfTuples=\"1\" format=\"ascii\">{}</DataArray>\n",
+            self.time
+        ));
+        xml.push_str("    </FieldData>\n");
+        xml.push_str(&format!(
+            "    <Piece NumberOfPoints=\"{}\" NumberOfCells=\"{}\">\n",
+            n, n
+        ));
+
+        xml.push_str("      <PointData Scalars=\"mass\" Vectors=\"velocity\">\n");
+        xml.push_str(&array("Float32", "mass", 1, join(self.masses.iter())));
+        xml.push_str(&array(
+            "Float32",
+            "diameter",
+            1,
+            join(self.diameters.iter()),
+        ));
+        xml.push_str(&array(
+            "Float32",
+            "velocity",
+            3,
+            join(velocity_vectors(self).flatten()),
+        ));
+        xml.push_str(&array("Int32", "id", 1, join(self.ids.iter())));
+        xml.push_str("      </PointData>\n");
+
+        xml.push_str("      <Points>\n");
+        xml.push_str(&array("Float32", "Points", 3, join(points(self).flatten())));
+        xml.push_str("      </Points>\n");
+
+        xml.push_str("      <Cells>\n");
+        xml.push_str(&array("Int32", "connectivity", 1, join(0..n)));
+        xml.push_str(&array("Int32", "offsets", 1, join(1..=n)));
+        xml.push_str(&array(
+            "UInt8",
+            "types",
+            1,
+            join((0..n).map(|_| VTK_VERTEX)),
+        ));
+        xml.push_str("      </Cells>\n");
+
+        xml.push_str("    </Piece>\n  </UnstructuredGrid>\n</VTKFile>\n");
+        xml
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::particle::Particle;
+    use crate::vector2::Vector2;
+
+    fn pair() -> Simulation {
+        let mut sim = Simulation::new(vec![
+            Particle::new(
+                2.0,
+                1.0,
+                Vector2::new(1.0, 2.0),
+                Vector2::new(0.5, -0.5),
+                [255.0, 255.0, 255.0],
+            ),
+            Particle::new(
+                3.0,
+                1.0,
+                Vector2::new(4.0, 5.0),
+                Vector2::new(0.0, 1.0),
+                [255.0, 255.0, 255.0],
+            ),
+        ]);
+        sim.set_ids(vec![10, 11]);
+        sim.time = 1.5;
+        sim
+    }
+
+    #[test]
+    fn test_legacy_ascii() {
+        let vtk = String::from_utf8(pair().to_vtk(VtkFormat::LegacyAscii)).unwrap();
+
+        assert!(vtk.starts_with("# vtk DataFile Version 3.0\n"));
+        assert!(vtk.contains("\nTIME 1 1 double\n1.5\n"));
+        assert!(vtk.contains("\nPOINTS 2 float\n1 2 0\n4 5 0\n"));
+        assert!(vtk.contains("\nCELLS 2 4\n1 0\n1 1\nCELL_TYPES 2\n1\n1\n"));
+        assert!(vtk.contains("\nSCALARS mass float 1\nLOOKUP_TABLE default\n2\n3\n"));
+        assert!(vtk.contains("\nVECTORS velocity float\n0.5 -0.5 0\n0 1 0\n"));
+        assert!(vtk.ends_with("SCALARS id int 1\nLOOKUP_TABLE default\n10\n11\n"));
+    }
+
+    #[test]
+    fn test_legacy_binary_is_big_endian() {
+        let vtk = pair().to_vtk(VtkFormat::LegacyBinary);
+
+        let header = b"\nPOINTS 2 float\n";
+        let start = vtk
+            .windows(header.len())
+            .position(|window| window == header)
+            .unwrap()
+            + header.len();
+        assert_eq!(&vtk[start..start + 4], &1.0f32.to_be_bytes());
+        assert_eq!(&vtk[start + 24..start + 26], b"\nC");
+
+        let ids = &vtk[vtk.len() - 9..];
+        assert_eq!(ids, &[0, 0, 0, 10, 0, 0, 0, 11, b'\n']);
+    }
+
+    #[test]
+    fn test_xml() {
+        let vtu = String::from_utf8(pair().to_vtk(VtkFormat::Xml)).unwrap();
+
+        assert!(vtu.contains("<Piece NumberOfPoints=\"2\" NumberOfCells=\"2\">"));
+        assert!(vtu.contains(
+            "Name=\"velocity\" NumberOfComponents=\"3\" format=\"ascii\">0.5 -0.5 0 0 1 0<"
+        ));
+        assert!(
+            vtu.contains("Name=\"Points\" NumberOfComponents=\"3\" format=\"ascii\">1 2 0 4 5 0<")
+        );
+        assert!(vtu.contains("Name=\"id\" NumberOfComponents=\"1\" format=\"ascii\">10 11<"));
+        assert!(vtu.contains("Name=\"offsets\" NumberOfComponents=\"1\" format=\"ascii\">1 2<"));
+        assert!(vtu.ends_with("</VTKFile>\n"));
+    }
+}
@@ -0,0 +1,638 @@
+use wasm_bindgen::prelude::*;
+
+use crate::density::DensityEstimator;
+use crate::diagnostics::PotentialMethod;
+use crate::few_body::FewBodyProblem;
+use crate::gadget::GadgetFormat;
+use crate::mass_distribution::MassDistribution;
+use crate::npy::TrajectoryStack;
+use crate::orbital_elements::OrbitalElements;
+use crate::particle::Particle;
+use crate::particle_generator::GenerationOptions;
+use crate::projection::ProjectionAxis;
+use crate::rectangle::Rectangle;
+use crate::scene::{Scene, SceneMetadata};
+use crate::simulation::Simulation;
+use crate::spatial_distribution::SpatialDistribution;
+use crate::tipsy::TipsyFormat;
+use crate::trajectory::{TrajectoryPlayer, TrajectoryRecorder};
+use crate::vector2::Vector2;
+use crate::vtk::VtkFormat;
+use crate::{csv, gadget, orbital_elements, particle_generator, radial_profile, tipsy};
+
+#[wasm_bindgen]
+pub struct SimulationWrapper {
+    inner: Simulation,
+}
+
+#[wasm_bindgen]
+impl SimulationWrapper {
+    #[wasm_bindgen(constructor)]
+    pub fn new(particles: Vec<Particle>) -> SimulationWrapper {
+        SimulationWrapper {
+            inner: Simulation::new(particles),
+        }
+    }
+
+    /// Builds a simulation from a JSON scene, reporting the first problem
+    /// found in malformed input.
+    pub fn import_scene_json(json: &str) -> Result<SimulationWrapper, JsValue> {
+        let inner = Scene::from_json(json)
+            .and_then(Simulation::from_scene)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        Ok(SimulationWrapper { inner })
+    }
+
+    /// The current state and physics parameters as a JSON scene.
+    pub fn export_scene_json(
+        &self,
+        name: String,
+        description: String,
+        author: String,
+    ) -> Result<String, JsValue> {
+        let metadata = SceneMetadata {
+            name,
+            description,
+            author,
+        };
+        self.inner
+            .to_scene(metadata)
+            .to_json()
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Reads particles from CSV. `columns` names the field held by each
+    /// column (empty to skip one); without it a header row is detected, or
+    /// the export order `id,x,y,vx,vy,mass,diameter,r,g,b` is assumed.
+    pub fn import_csv(
+        text: &str,
+        columns: Option<Vec<String>>,
+    ) -> Result<SimulationWrapper, JsValue> {
+        let to_js = |e: csv::CsvError| JsValue::from_str(&e.to_string());
+        let columns = columns
+            .map(|names| csv::columns_from_names(&names))
+            .transpose()
+            .map_err(to_js)?;
+        let inner = Simulation::from_csv(text, columns).map_err(to_js)?;
+        Ok(SimulationWrapper { inner })
+    }
+
+    pub fn export_csv(&self) -> String {
+        self.inner.to_csv()
+    }
+
+    /// Reads a little-endian GADGET-1/2 snapshot, dropping `axis` to
+    /// flatten it and coloring particles by type.
+    pub fn import_gadget(
+        bytes: &[u8],
+        axis: ProjectionAxis,
+        diameter: f32,
+    ) -> Result<SimulationWrapper, JsValue> {
+        let inner = gadget::GadgetSnapshot::from_bytes(bytes)
+            .and_then(|snapshot| Simulation::from_gadget(&snapshot, axis, diameter))
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        Ok(SimulationWrapper { inner })
+    }
+
+    /// GADGET snapshot in format 1 or 2 with every particle as type 1 and
+    /// z = 0.
+    pub fn export_gadget(&self, format: GadgetFormat) -> Result<Vec<u8>, JsValue> {
+        self.inner
+            .to_gadget()
+            .to_bytes(format)
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Reads a standard (XDR) or native TIPSY file, flattened along `axis`.
+    pub fn import_tipsy(
+        bytes: &[u8],
+        axis: ProjectionAxis,
+        diameter: f32,
+    ) -> Result<SimulationWrapper, JsValue> {
+        let snapshot = tipsy::TipsySnapshot::from_bytes(bytes)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        Ok(SimulationWrapper {
+            inner: Simulation::from_tipsy(&snapshot, axis, diameter),
+        })
+    }
+
+    /// TIPSY file with every particle as dark matter and z = 0.
+    pub fn export_tipsy(&self, format: TipsyFormat) -> Result<Vec<u8>, JsValue> {
+        self.inner
+            .to_tipsy()
+            .to_bytes(format)
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// One array as a NumPy `.npy` file, named as in `NPY_ARRAYS`.
+    pub fn export_npy(&self, name: &str) -> Result<Vec<u8>, JsValue> {
+        self.inner
+            .to_npy(name)
+            .ok_or_else(|| JsValue::from_str(&format!("Unknown array \"{}\"", name)))
+    }
+
+    /// Every array in one `.npz` archive, for `numpy.load`.
+    pub fn export_npz(&self) -> Result<Vec<u8>, JsValue> {
+        self.inner
+            .to_npz()
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Appends the current state to a trajectory for `.npz` export.
+    pub fn record_trajectory_frame(&self, stack: &mut TrajectoryStack) -> Result<(), JsValue> {
+        stack
+            .push(&self.inner)
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Adds a frame to `recorder` when the step count falls on its interval.
+    pub fn record_trajectory(&self, recorder: &mut TrajectoryRecorder) -> bool {
+        recorder.record(&self.inner)
+    }
+
+    /// Point cloud for ParaView with mass, velocity, diameter and ID
+    /// attributes, as a legacy `.vtk` or XML `.vtu` file.
+    pub fn export_vtk(&self, format: VtkFormat) -> Vec<u8> {
+        self.inner.to_vtk(format)
+    }
+
+    /// Resumes a run saved with `to_checkpoint`. Stepping it with `params()`
+    /// gives bit-identical results to the run that was never stopped.
+    pub fn from_checkpoint(bytes: &[u8]) -> Result<SimulationWrapper, JsValue> {
+        let inner =
+            Simulation::from_checkpoint(bytes).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        Ok(SimulationWrapper { inner })
+    }
+
+    /// Snapshot plus the random generator and drift tracking state.
+    pub fn to_checkpoint(&self) -> Vec<u8> {
+        self.inner.to_checkpoint()
+    }
+
+    /// Restarts the random sequence behind sampled diagnostics from `seed`.
+    pub fn set_seed(&mut self, seed: u64) {
+        self.inner.seed(seed);
+    }
+
+    /// Restores a simulation saved with `to_bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<SimulationWrapper, JsValue> {
+        let inner = Simulation::from_bytes(bytes).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        Ok(SimulationWrapper { inner })
+    }
+
+    /// Versioned, checksummed binary snapshot of the whole simulation state.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.inner.to_bytes()
+    }
+
+    pub fn step(
+        &mut self,
+        world_width: f32,
+        world_height: f32,
+        gravity: f32,
+        epsilon: f32,
+        time_step: f32,
+    ) {
+        let world_size = Vector2::new(world_width, world_height);
+        self.inner.step(world_size, gravity, epsilon, time_step);
+    }
+
+    pub fn ids_ptr(&self) -> *const i32 {
+        self.inner.ids.as_ptr()
+    }
+
+    pub fn positions_x_ptr(&self) -> *const f32 {
+        self.inner.positions_x.as_ptr()
+    }
+
+    pub fn positions_y_ptr(&self) -> *const f32 {
+        self.inner.positions_y.as_ptr()
+    }
+
+    pub fn velocities_x_ptr(&self) -> *const f32 {
+        self.inner.velocities_x.as_ptr()
+    }
+
+    pub fn velocities_y_ptr(&self) -> *const f32 {
+        self.inner.velocities_y.as_ptr()
+    }
+
+    pub fn masses_ptr(&self) -> *const f32 {
+        self.inner.masses.as_ptr()
+    }
+
+    pub fn diameters_ptr(&self) -> *const f32 {
+        self.inner.diameters.as_ptr()
+    }
+
+    pub fn colors_ptr(&self) -> *const f32 {
+        self.inner.colors.as_ptr()
+    }
+
+    pub fn densities_ptr(&self) -> *const f32 {
+        self.inner.densities.as_ptr()
+    }
+
+    pub fn count(&self) -> usize {
+        self.inner.count
+    }
+
+    /// Recomputes the per-particle densities behind `densities_ptr` from
+    /// each particle's `k` nearest neighbors.
+    pub fn estimate_densities(&mut self, k: usize, estimator: DensityEstimator) {
+        self.inner.estimate_densities(k, estimator);
+    }
+
+    /// Current index of the particle with the given ID.
+    pub fn index_of(&self, id: i32) -> Option<usize> {
+        self.inner.index_of(id)
+    }
+
+    pub fn set_particle_position(&mut self, id: i32, x: f32, y: f32) -> Result<(), JsValue> {
+        let index = self.index_for(id)?;
+        self.inner
+            .set_position(index, Vector2::new(x, y))
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    pub fn set_particle_velocity(&mut self, id: i32, vx: f32, vy: f32) -> Result<(), JsValue> {
+        let index = self.index_for(id)?;
+        self.inner
+            .set_velocity(index, Vector2::new(vx, vy))
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    pub fn set_particle_mass(&mut self, id: i32, mass: f32) -> Result<(), JsValue> {
+        let index = self.index_for(id)?;
+        self.inner
+            .set_mass(index, mass)
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    pub fn set_particle_color(&mut self, id: i32, r: f32, g: f32, b: f32) -> Result<(), JsValue> {
+        let index = self.index_for(id)?;
+        self.inner
+            .set_color(index, [r, g, b])
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Kicks the particle by `impulse / mass`, e.g. when flung with the mouse.
+    pub fn apply_impulse(
+        &mut self,
+        id: i32,
+        impulse_x: f32,
+        impulse_y: f32,
+    ) -> Result<(), JsValue> {
+        let index = self.index_for(id)?;
+        self.inner
+            .apply_impulse(index, Vector2::new(impulse_x, impulse_y))
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Index, distance and full state of the particle closest to `(x, y)`,
+    /// or `undefined` when none lies within `max_radius`.
+    pub fn pick_particle(&self, x: f32, y: f32, max_radius: f32) -> Result<JsValue, JsValue> {
+        let picked = self.inner.pick_particle(Vector2::new(x, y), max_radius);
+        Ok(serde_wasm_bindgen::to_value(&picked)?)
+    }
+
+    /// Indices of the `k` particles closest to `(x, y)`, nearest first.
+    pub fn nearest_particles(&self, x: f32, y: f32, k: usize) -> Vec<usize> {
+        self.inner.nearest_particles(Vector2::new(x, y), k)
+    }
+
+    pub fn particles_within_radius(&self, x: f32, y: f32, radius: f32) -> Vec<usize> {
+        self.inner
+            .particles_within_radius(Vector2::new(x, y), radius)
+    }
+
+    /// Indices of the particles inside the rectangle with top-left corner `(x, y)`.
+    pub fn particles_within_rectangle(
+        &self,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+    ) -> Vec<usize> {
+        let rectangle = Rectangle::new(Vector2::new(x, y), width, height);
+        self.inner.particles_within_rectangle(&rectangle)
+    }
+
+    /// Energies, momenta and virial ratio of the current state, under the
+    /// physics of the last step.
+    pub fn diagnostics(&self, method: PotentialMethod) -> Result<JsValue, JsValue> {
+        let diagnostics = self.inner.diagnostics(method);
+        Ok(serde_wasm_bindgen::to_value(&diagnostics)?)
+    }
+
+    /// Records relative energy and angular momentum error every `interval`
+    /// steps, keeping the latest `capacity` samples.
+    pub fn track_drift(&mut self, interval: u32, capacity: usize, method: PotentialMethod) {
+        self.inner.track_drift(interval as u64, capacity, method);
+    }
+
+    pub fn stop_tracking_drift(&mut self) {
+        self.inner.drift = None;
+    }
+
+    /// Number of valid drift samples. They start at `drift_start()` in the
+    /// buffers below and wrap around at `drift_capacity()`.
+    pub fn drift_len(&self) -> usize {
+        self.inner
+            .drift
+            .as_ref()
+            .map_or(0, |tracker| tracker.sample_count())
+    }
+
+    pub fn drift_start(&self) -> usize {
+        self.inner
+            .drift
+            .as_ref()
+            .map_or(0, |tracker| tracker.start())
+    }
+
+    pub fn drift_capacity(&self) -> usize {
+        self.inner
+            .drift
+            .as_ref()
+            .map_or(0, |tracker| tracker.capacity())
+    }
+
+    pub fn drift_times_ptr(&self) -> *const f64 {
+        self.inner
+            .drift
+            .as_ref()
+            .map_or(std::ptr::null(), |tracker| tracker.times.as_ptr())
+    }
+
+    pub fn drift_energy_errors_ptr(&self) -> *const f32 {
+        self.inner
+            .drift
+            .as_ref()
+            .map_or(std::ptr::null(), |tracker| tracker.energy_errors.as_ptr())
+    }
+
+    pub fn drift_angular_momentum_errors_ptr(&self) -> *const f32 {
+        self.inner
+            .drift
+            .as_ref()
+            .map_or(std::ptr::null(), |tracker| {
+                tracker.angular_momentum_errors.as_ptr()
+            })
+    }
+
+    /// Drift samples oldest first as `[time, energy error, angular momentum error]`,
+    /// for callers that would rather not unwrap the ring buffer themselves.
+    pub fn drift_history(&self) -> Result<JsValue, JsValue> {
+        let samples = self
+            .inner
+            .drift
+            .as_ref()
+            .map_or_else(Vec::new, |tracker| tracker.samples());
+        Ok(serde_wasm_bindgen::to_value(&samples)?)
+    }
+
+    /// Barnes-Hut opening angle used by `step` and tree potentials.
+    pub fn set_theta(&mut self, theta: f32) {
+        self.inner.theta = theta;
+    }
+
+    pub fn theta(&self) -> f32 {
+        self.inner.theta
+    }
+
+    /// Tree force error against direct summation over `sample_size` random
+    /// particles, for choosing `theta` and softening for a scene.
+    pub fn force_accuracy(
+        &mut self,
+        sample_size: usize,
+        gravity: f32,
+        epsilon: f32,
+        theta: f32,
+    ) -> Result<JsValue, JsValue> {
+        let accuracy = self
+            .inner
+            .force_accuracy(sample_size, gravity, epsilon, theta);
+        Ok(serde_wasm_bindgen::to_value(&accuracy)?)
+    }
+
+    /// Density, enclosed mass, circular velocity and velocity dispersion in
+    /// `bins` annuli around the center of mass. A non-positive
+    /// `outer_radius` reaches the farthest particle.
+    pub fn radial_profile(
+        &self,
+        gravity: f32,
+        bins: usize,
+        inner_radius: f32,
+        outer_radius: f32,
+        logarithmic: bool,
+    ) -> Result<JsValue, JsValue> {
+        let profile =
+            self.inner
+                .radial_profile(gravity, bins, inner_radius, outer_radius, logarithmic);
+        Ok(serde_wasm_bindgen::to_value(&profile)?)
+    }
+
+    /// Radii enclosing each mass fraction, 10%, 50% and 90% by default.
+    pub fn lagrangian_radii(&self, fractions: Option<Vec<f32>>) -> Vec<f32> {
+        let fractions =
+            fractions.unwrap_or_else(|| radial_profile::DEFAULT_LAGRANGIAN_FRACTIONS.to_vec());
+        self.inner.lagrangian_radii(&fractions)
+    }
+
+    /// Friends-of-friends groups with at least `min_members` particles,
+    /// heaviest first, plus each particle's group ID (-1 when ungrouped).
+    pub fn friends_of_friends(
+        &self,
+        linking_length: f32,
+        min_members: usize,
+    ) -> Result<JsValue, JsValue> {
+        let groups = self.inner.friends_of_friends(linking_length, min_members);
+        Ok(serde_wasm_bindgen::to_value(&groups)?)
+    }
+
+    /// World size, gravity, softening and time step of the last `step`.
+    pub fn params(&self) -> Result<JsValue, JsValue> {
+        Ok(serde_wasm_bindgen::to_value(&self.inner.params)?)
+    }
+
+    pub fn time(&self) -> f64 {
+        self.inner.time
+    }
+
+    pub fn step_count(&self) -> u64 {
+        self.inner.step_count
+    }
+
+    /// Osculating orbits of every particle, relative to `primary` when given
+    /// or to each particle's most-bound neighbor otherwise.
+    pub fn orbital_elements(
+        &self,
+        gravity: f32,
+        primary: Option<usize>,
+    ) -> Result<JsValue, JsValue> {
+        let reports = match primary {
+            Some(primary) if primary >= self.inner.count => {
+                return Err(JsValue::from_str("Primary index out of range"));
+            }
+            Some(primary) => self.inner.orbits_about(primary, gravity),
+            None => self.inner.orbits_about_most_bound(gravity),
+        };
+        Ok(serde_wasm_bindgen::to_value(&reports)?)
+    }
+
+    pub fn binaries(&self, gravity: f32) -> Result<JsValue, JsValue> {
+        let binaries = self.inner.find_binaries(gravity);
+        Ok(serde_wasm_bindgen::to_value(&binaries)?)
+    }
+}
+
+impl SimulationWrapper {
+    fn index_for(&self, id: i32) -> Result<usize, JsValue> {
+        self.inner
+            .index_of(id)
+            .ok_or_else(|| JsValue::from_str("Unknown particle ID"))
+    }
+}
+
+/// Starts recording frames every `interval` steps, rounded to multiples of
+/// `precision`, with a keyframe every `keyframe_interval` frames.
+#[wasm_bindgen]
+pub fn create_trajectory_recorder(
+    interval: u32,
+    precision: f32,
+    record_velocities: bool,
+    keyframe_interval: usize,
+) -> Result<TrajectoryRecorder, JsValue> {
+    TrajectoryRecorder::new(interval, precision, record_velocities, keyframe_interval)
+        .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+#[wasm_bindgen]
+pub fn create_uniform_distribution(mean: f32, deviation: f32) -> Result<MassDistribution, JsValue> {
+    MassDistribution::uniform(mean, deviation).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+#[wasm_bindgen]
+pub fn create_normal_distribution(mean: f32, std_dev: f32) -> Result<MassDistribution, JsValue> {
+    MassDistribution::normal(mean, std_dev).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+#[wasm_bindgen]
+pub fn create_log_normal_distribution(
+    median: f32,
+    sigma: f32,
+) -> Result<MassDistribution, JsValue> {
+    MassDistribution::log_normal(median, sigma).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Power law masses for `GenerationOptions`, rejecting bounds outside
+/// `0 < min <= max`.
+#[wasm_bindgen]
+pub fn create_power_law_distribution(
+    min: f32,
+    max: f32,
+    alpha: f32,
+) -> Result<MassDistribution, JsValue> {
+    MassDistribution::power_law(min, max, alpha).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+#[wasm_bindgen]
+pub fn create_salpeter_distribution(min: f32, max: f32) -> Result<MassDistribution, JsValue> {
+    MassDistribution::salpeter(min, max).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+#[wasm_bindgen]
+pub fn create_kroupa_distribution(
+    min: f32,
+    max: f32,
+    break_mass: f32,
+) -> Result<MassDistribution, JsValue> {
+    MassDistribution::kroupa(min, max, break_mass).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Spiral arms for `GenerationOptions`, rejecting pitches outside (0, π/2).
+#[wasm_bindgen]
+pub fn create_spiral_distribution(
+    arms: u32,
+    pitch: f32,
+    inner_radius: f32,
+    outer_radius: f32,
+    spread: f32,
+) -> Result<SpatialDistribution, JsValue> {
+    SpatialDistribution::spiral(arms, pitch, inner_radius, outer_radius, spread)
+        .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// A recorded trajectory as one `.npz` archive, for `numpy.load`.
+#[wasm_bindgen]
+pub fn export_trajectory_npz(stack: &TrajectoryStack) -> Result<Vec<u8>, JsValue> {
+    stack
+        .to_npz()
+        .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Plays back a trajectory saved with `TrajectoryRecorder::to_bytes`.
+#[wasm_bindgen]
+pub fn load_trajectory(bytes: &[u8]) -> Result<TrajectoryPlayer, JsValue> {
+    TrajectoryPlayer::from_bytes(bytes).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+#[wasm_bindgen]
+pub fn generate_particles(
+    number: usize,
+    world_width: f32,
+    world_height: f32,
+    mass: f32,
+    mass_deviation: f32,
+    diameter: f32,
+    options: Option<GenerationOptions>,
+) -> Result<SimulationWrapper, JsValue> {
+    let world_size = Vector2::new(world_width, world_height);
+
+    let particles = particle_generator::generate(
+        number,
+        world_size,
+        mass,
+        mass_deviation,
+        diameter,
+        &options.unwrap_or_default(),
+    )
+    .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    Ok(SimulationWrapper {
+        inner: Simulation::new(particles),
+    })
+}
+
+#[wasm_bindgen]
+pub fn generate_planetary_system(
+    central: Particle,
+    elements: Vec<OrbitalElements>,
+    gravity: f32,
+) -> Result<SimulationWrapper, JsValue> {
+    let particles = orbital_elements::planetary_system(central, &elements, gravity)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    Ok(SimulationWrapper {
+        inner: Simulation::new(particles),
+    })
+}
+
+#[wasm_bindgen]
+pub fn generate_few_body(
+    problem: FewBodyProblem,
+    world_width: f32,
+    world_height: f32,
+    length: f32,
+    mass: f32,
+    gravity: f32,
+    diameter: f32,
+) -> SimulationWrapper {
+    let center = Vector2::new(world_width / 2.0, world_height / 2.0);
+    let particles = problem.particles(center, length, mass, gravity, diameter);
+
+    SimulationWrapper {
+        inner: Simulation::new(particles),
+    }
+}